@@ -1,342 +1,504 @@
-use std::collections::HashSet;
-use std::collections::VecDeque;
-
-enum OpCodes {
-	Add = 1,
-	Mul,
-	Input,
-	Output,
-	JIT,
-	JIF,
-	LT,
-	EQ,
-	RA,
-	Halt = 99,
+use intcode::ascii::Ascii;
+use intcode::{Limits, OpMachine, RunState};
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::process;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Direction {
+	Up,
+	Down,
+	Left,
+	Right,
 }
 
-enum OpModes {
-	Parametric,
-	Immediate,
-	Relative,
-}
-
-impl OpCodes {
-	fn from(code: i64) -> OpCodes {
-		match code {
-			1 => OpCodes::Add,
-			2 => OpCodes::Mul,
-			3 => OpCodes::Input,
-			4 => OpCodes::Output,
-			5 => OpCodes::JIT,
-			6 => OpCodes::JIF,
-			7 => OpCodes::LT,
-			8 => OpCodes::EQ,
-			9 => OpCodes::RA,
-			_ => OpCodes::Halt,
+impl Direction {
+	fn from(ch: char) -> Option<Direction> {
+		match ch {
+			'^' => Some(Direction::Up),
+			'v' => Some(Direction::Down),
+			'<' => Some(Direction::Left),
+			'>' => Some(Direction::Right),
+			_ => None,
 		}
 	}
 
-	fn param_count(&self) -> usize {
+	fn symbol(&self) -> char {
 		match self {
-			OpCodes::Add | OpCodes::Mul | OpCodes::LT | OpCodes::EQ => 4,
-			OpCodes::Input | OpCodes::Output | OpCodes::RA => 2,
-			_ => 0,
+			Direction::Up => '^',
+			Direction::Down => 'v',
+			Direction::Left => '<',
+			Direction::Right => '>',
 		}
 	}
-}
-
-/**
- * OpMachine, it handles the operations step by step.
- * It uses the OpCodes struct above.
- */
-struct OpMachine {
-	ip: usize,
-	program: Vec<i64>,
-	inputs: VecDeque<i64>,
-	outputs: VecDeque<i64>,
-	base: i64,
-}
 
-impl OpMachine {
-	fn get_value(&self, mode: &OpModes, position: usize) -> i64 {
-		match mode {
-			OpModes::Immediate => self.program[position],
-			OpModes::Relative => self.program[(self.base + self.program[position]) as usize],
-			_ => self.program[self.program[position] as usize],
+	fn delta(&self) -> (i64, i64) {
+		match self {
+			Direction::Up => (0, -1),
+			Direction::Down => (0, 1),
+			Direction::Left => (-1, 0),
+			Direction::Right => (1, 0),
 		}
 	}
 
-	fn store(&mut self, value: i64, position: usize, mode: &OpModes) {
-		let loc = match mode {
-			OpModes::Relative => (self.base + self.program[position]) as usize,
-			_ => self.program[position] as usize,
-		};
-
-		self.program[loc] = value;
+	fn left(&self) -> Direction {
+		match self {
+			Direction::Up => Direction::Left,
+			Direction::Left => Direction::Down,
+			Direction::Down => Direction::Right,
+			Direction::Right => Direction::Up,
+		}
 	}
 
-	fn add(&mut self, modes: Vec<OpModes>) {
-		let verb = self.get_value(&modes[0], self.ip + 1);
-		let noun = self.get_value(&modes[1], self.ip + 2);
-		self.store(verb + noun, self.ip + 3, &modes[2]);
+	fn right(&self) -> Direction {
+		self.left().left().left()
 	}
+}
 
-	fn mul(&mut self, modes: Vec<OpModes>) {
-		let verb = self.get_value(&modes[0], self.ip + 1);
-		let noun = self.get_value(&modes[1], self.ip + 2);
-		self.store(verb * noun, self.ip + 3, &modes[2]);
-	}
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Tile {
+	Open,
+	Scaffold,
+	/* the robot stands on scaffold, facing the direction */
+	Robot(Direction),
+	/* the robot fell off and is tumbling through space */
+	Tumbling,
+}
 
-	fn output(&mut self, mode: &OpModes) {
-		let verb = self.get_value(mode, self.ip + 1);
-		self.outputs.push_back(verb);
+impl Tile {
+	fn from(ch: char) -> Option<Tile> {
+		match ch {
+			'.' => Some(Tile::Open),
+			'#' => Some(Tile::Scaffold),
+			'X' => Some(Tile::Tumbling),
+			_ => Direction::from(ch).map(Tile::Robot),
+		}
 	}
 
-	fn input(&mut self, mode: &OpModes) {
-		if self.inputs.len() == 0 {
-			println!("Need more");
+	fn symbol(&self) -> char {
+		match self {
+			Tile::Open => '.',
+			Tile::Scaffold => '#',
+			Tile::Robot(direction) => direction.symbol(),
+			Tile::Tumbling => 'X',
 		}
-		let value = self.inputs.pop_front().unwrap();
-		self.store(value, self.ip + 1, mode);
 	}
+}
 
-	fn jump_if_true(&mut self, modes: Vec<OpModes>) {
-		let verb = self.get_value(&modes[0], self.ip + 1);
-		let noun = self.get_value(&modes[1], self.ip + 2);
+/**
+ * Where the robot is, its direction is None when it is tumbling.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Pose {
+	x: i64,
+	y: i64,
+	facing: Option<Direction>,
+}
 
-		if verb != 0 {
-			self.ip = noun as usize;
-		} else {
-			self.ip += 3;
+/**
+ * A frame of the camera, the scaffold and the robot on it.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Grid {
+	tiles: Vec<Vec<Tile>>,
+	robot: Option<Pose>,
+}
+
+impl Grid {
+	/// Parse a frame, None when it holds something that is not a tile.
+	fn parse(text: &str) -> Option<Grid> {
+		let mut tiles = Vec::new();
+		let mut robot = None;
+
+		for (y, line) in text.lines().filter(|line| !line.is_empty()).enumerate() {
+			let mut row = Vec::new();
+			for (x, ch) in line.chars().enumerate() {
+				let tile = Tile::from(ch)?;
+				let facing = match tile {
+					Tile::Robot(direction) => Some(direction),
+					_ => None,
+				};
+
+				if let Tile::Robot(_) | Tile::Tumbling = tile {
+					robot = Some(Pose {
+						x: x as i64,
+						y: y as i64,
+						facing,
+					});
+				}
+
+				row.push(tile);
+			}
+
+			tiles.push(row);
 		}
-	}
 
-	fn register_input(&mut self, input: i64) {
-		self.inputs.push_back(input);
-	}
+		if tiles.is_empty() {
+			return None;
+		}
 
-	fn has_output(&self) -> bool {
-		return !self.outputs.is_empty();
+		Some(Grid { tiles, robot })
 	}
 
-	fn jump_if_false(&mut self, modes: Vec<OpModes>) {
-		let verb = self.get_value(&modes[0], self.ip + 1);
-		let noun = self.get_value(&modes[1], self.ip + 2);
-		if verb == 0 {
-			self.ip = noun as usize;
-		} else {
-			self.ip += 3;
-		}
+	fn width(&self) -> i64 {
+		self.tiles.iter().map(|row| row.len()).max().unwrap_or(0) as i64
 	}
 
-	fn less_than(&mut self, modes: Vec<OpModes>) {
-		let verb = self.get_value(&modes[0], self.ip + 1);
-		let noun = self.get_value(&modes[1], self.ip + 2);
+	fn height(&self) -> i64 {
+		self.tiles.len() as i64
+	}
 
-		if verb < noun {
-			self.store(1, self.ip + 3, &modes[2]);
-		} else {
-			self.store(0, self.ip + 3, &modes[2]);
+	fn get(&self, x: i64, y: i64) -> Tile {
+		if x < 0 || y < 0 {
+			return Tile::Open;
 		}
-	}
 
-	fn equal(&mut self, modes: Vec<OpModes>) {
-		let verb = self.get_value(&modes[0], self.ip + 1);
-		let noun = self.get_value(&modes[1], self.ip + 2);
-		if verb == noun {
-			self.store(1, self.ip + 3, &modes[2]);
-		} else {
-			self.store(0, self.ip + 3, &modes[2]);
+		match self
+			.tiles
+			.get(y as usize)
+			.and_then(|row| row.get(x as usize))
+		{
+			Some(tile) => *tile,
+			None => Tile::Open,
 		}
 	}
 
-	fn relative_add(&mut self, modes: Vec<OpModes>) {
-		let verb = self.get_value(&modes[0], self.ip + 1);
-		self.base += verb;
+	/// The robot stands on scaffold, unless it is tumbling.
+	fn is_scaffold(&self, x: i64, y: i64) -> bool {
+		matches!(self.get(x, y), Tile::Scaffold | Tile::Robot(_))
 	}
 
-	fn get_mode(&self, input: i64) -> OpModes {
-		if input % 10 == 1 {
-			return OpModes::Immediate;
-		} else if input % 10 == 2 {
-			return OpModes::Relative;
+	/// The scaffold with scaffold on all four sides.
+	fn intersections(&self) -> Vec<(i64, i64)> {
+		let mut intersections = Vec::new();
+		for y in 0..self.height() {
+			for x in 0..self.width() {
+				let neighbours = [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)];
+				if self.is_scaffold(x, y)
+					&& neighbours.iter().all(|(x, y)| self.is_scaffold(*x, *y))
+				{
+					intersections.push((x, y));
+				}
+			}
 		}
 
-		OpModes::Parametric
+		intersections
 	}
 
-	fn step(&mut self) -> bool {
-		let opcode = self.program[self.ip];
-		let code = OpCodes::from(opcode % 100);
-		let modes = vec![
-			self.get_mode(opcode / 100),
-			self.get_mode(opcode / 1000),
-			self.get_mode(opcode / 10000),
-		];
+	fn alignment(&self) -> i64 {
+		self.intersections().iter().map(|(x, y)| x * y).sum()
+	}
 
-		match code {
-			OpCodes::Add => self.add(modes),
-			OpCodes::Mul => self.mul(modes),
-			OpCodes::Input => self.input(&modes[0]),
-			OpCodes::Output => self.output(&modes[0]),
-			OpCodes::JIT => self.jump_if_true(modes),
-			OpCodes::JIF => self.jump_if_false(modes),
-			OpCodes::LT => self.less_than(modes),
-			OpCodes::EQ => self.equal(modes),
-			OpCodes::RA => self.relative_add(modes),
-			OpCodes::Halt => return false,
+	/// Draw the grid with the cells of the path as o, and the ones
+	/// the path crosses more than once as +.
+	fn render(&self, path: &[(i64, i64)]) -> String {
+		let mut visits: HashMap<(i64, i64), usize> = HashMap::new();
+		for cell in path {
+			*visits.entry(*cell).or_insert(0) += 1;
 		}
 
-		self.ip += code.param_count();
-		true
-	}
-
-	fn run_until_output(&mut self) -> Option<i64> {
-		while self.step() {
-			if self.has_output() {
-				return Some(self.outputs.pop_front().unwrap());
+		let mut output = String::new();
+		for y in 0..self.height() {
+			for x in 0..self.width() {
+				output.push(match (self.get(x, y), visits.get(&(x, y))) {
+					(Tile::Scaffold, Some(1)) => 'o',
+					(Tile::Scaffold, Some(_)) => '+',
+					(tile, _) => tile.symbol(),
+				});
 			}
+
+			output.push('\n');
 		}
 
-		return None;
+		output
 	}
+}
 
-	fn run(&mut self) {
-		let mut result = self.run_until_output();
-		while result.is_some() {
-			println!("{}", result.unwrap());
-			result = self.run_until_output();
-		}
+impl fmt::Display for Grid {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", self.render(&[]))
 	}
 }
 
-fn index(x: i64, y: i64) -> String {
-	return format!("{}_{}", x, y);
+fn scan(program: Vec<i64>) -> Grid {
+	let mut camera = Ascii::new(OpMachine::new(program));
+	Grid::parse(&camera.read_until_prompt().text).expect("the camera did not send a frame")
 }
 
-fn silver(program: Vec<i64>) -> i64 {
-	let mut machine = OpMachine {
-		program: program.clone(),
-		inputs: VecDeque::new(),
-		outputs: VecDeque::new(),
-		base: 0,
-		ip: 0,
+fn silver(grid: &Grid) -> i64 {
+	grid.alignment()
+}
+
+/**
+ * The way along the scaffold, as moves for the robot and
+ * as the cells it passes, in order.
+ */
+struct Path {
+	moves: Vec<String>,
+	cells: Vec<(i64, i64)>,
+}
+
+/**
+ * Follow the scaffold from the robot to its end, going straight on
+ * at every intersection. Every move is a turn and the number of
+ * steps forward, like R,8. Only the first move may lack a turn,
+ * or need two when the scaffold is behind the robot.
+ */
+fn trace(grid: &Grid) -> Path {
+	let mut path = Path {
+		moves: Vec::new(),
+		cells: Vec::new(),
 	};
 
-	let mut map: HashSet<String> = HashSet::new();
-	let mut x = 0;
-	let mut y = 0;
-	let mut max_x = 0;
+	let (mut x, mut y, mut facing) = match grid.robot {
+		Some(Pose {
+			x,
+			y,
+			facing: Some(facing),
+		}) => (x, y, facing),
+		_ => return path,
+	};
+	path.cells.push((x, y));
 
 	loop {
-		let output = machine.run_until_output();
-		if !output.is_some() {
-			break;
+		let turns = [
+			("", facing),
+			("L", facing.left()),
+			("R", facing.right()),
+			("R,R", facing.right().right()),
+		];
+		let turn = turns
+			.iter()
+			.take(if path.moves.is_empty() { 4 } else { 3 })
+			.find(|(_, direction)| {
+				let (dx, dy) = direction.delta();
+				grid.is_scaffold(x + dx, y + dy)
+			});
+
+		let (turn, direction) = match turn {
+			Some(turn) => *turn,
+			None => return path,
+		};
+
+		facing = direction;
+		let (dx, dy) = facing.delta();
+
+		let mut steps = 0;
+		while grid.is_scaffold(x + dx, y + dy) {
+			x += dx;
+			y += dy;
+			steps += 1;
+			path.cells.push((x, y));
 		}
 
-		match output.unwrap() {
-			10 => {
-				y += 1;
+		if turn.is_empty() {
+			path.moves.push(steps.to_string());
+		} else {
+			path.moves.push(format!("{},{}", turn, steps));
+		}
+	}
+}
 
-				if max_x < x {
-					max_x = x;
-				}
+/**
+ * The main routine and the movement functions A, B and C.
+ */
+#[derive(Debug, PartialEq, Eq)]
+struct Routines {
+	main: String,
+	functions: Vec<String>,
+}
 
-				x = 0;
-			}
-			35 => {
-				map.insert(index(x, y));
-				x += 1;
-			}
-			_ => {
-				x += 1;
+/* the most characters a routine can have, without the newline */
+const ROUTINE_LIMIT: usize = 20;
+const FUNCTIONS: usize = 3;
+
+fn length(moves: &[String]) -> usize {
+	moves.iter().map(|m| m.len()).sum::<usize>() + moves.len().saturating_sub(1)
+}
+
+/**
+ * Depth first search for the functions, which are picked in the
+ * order the main routine first calls them.
+ */
+fn split<'a>(
+	moves: &'a [String],
+	functions: &mut Vec<&'a [String]>,
+	calls: &mut Vec<usize>,
+) -> bool {
+	if moves.is_empty() {
+		return true;
+	}
+
+	/* every call takes a letter and a comma */
+	if 2 * calls.len() + 1 > ROUTINE_LIMIT {
+		return false;
+	}
+
+	for function in 0..functions.len() {
+		let body = functions[function];
+		if moves.starts_with(body) {
+			calls.push(function);
+			if split(&moves[body.len()..], functions, calls) {
+				return true;
 			}
+
+			calls.pop();
 		}
 	}
 
-	let mut output = 0;
-	for i in 0..y + 1 {
-		for j in 0..max_x + 1 {
-			let mut valid = true;
-			valid = valid && map.contains(&index(j - 1, i));
-			valid = valid && map.contains(&index(j + 1, i));
-			valid = valid && map.contains(&index(j, i + 1));
-			valid = valid && map.contains(&index(j, i - 1));
-
-			if valid {
-				output += i * j;
+	if functions.len() < FUNCTIONS {
+		for size in 1..=moves.len() {
+			if length(&moves[..size]) > ROUTINE_LIMIT {
+				break;
 			}
+
+			functions.push(&moves[..size]);
+			calls.push(functions.len() - 1);
+			if split(&moves[size..], functions, calls) {
+				return true;
+			}
+
+			functions.pop();
+			calls.pop();
 		}
 	}
 
-	output
+	false
 }
 
 /**
- * Reversed it by hand on paper.
- * A: R,8,L,12,R,4,R,4
- * B: R,8,L,10,L,12,R,4
- * C: R,8,L,10,R,8
- * Path: B,A,B,C,B,A,C,A,C,A
+ * Split the moves into at most three functions, and a main routine
+ * calling them, all within the routine limit. None when there is no
+ * such split.
  */
-fn gold(program: Vec<i64>) -> i64 {
-	let path = "B,A,B,C,B,A,C,A,C,A\n";
-	let sequence_a = "R,8,L,12,R,4,R,4\n";
-	let sequence_b = "R,8,L,10,L,12,R,4\n";
-	let sequence_c = "R,8,L,10,R,8\n";
+fn compress(moves: &[String]) -> Option<Routines> {
+	let mut functions = Vec::new();
+	let mut calls = Vec::new();
 
-	let mut program = program.clone();
-	program[0] = 2;
+	/* without moves there is nothing to call, the main routine would be empty */
+	if moves.is_empty() || !split(moves, &mut functions, &mut calls) {
+		return None;
+	}
 
-	let mut machine = OpMachine {
-		program: program,
-		inputs: VecDeque::new(),
-		outputs: VecDeque::new(),
-		base: 0,
-		ip: 0,
-	};
+	let main: Vec<String> = calls
+		.iter()
+		.map(|call| ((b'A' + *call as u8) as char).to_string())
+		.collect();
 
-	for i in path.chars() {
-		machine.register_input(i as i64);
-	}
+	Some(Routines {
+		main: main.join(","),
+		functions: functions
+			.iter()
+			.map(|function| function.join(","))
+			.collect(),
+	})
+}
 
-	for i in sequence_a.chars() {
-		machine.register_input(i as i64);
-	}
+/**
+ * What the robot reported, and the frames of the video feed
+ * when it was asked for.
+ */
+struct Report {
+	dust: i64,
+	frames: Vec<Grid>,
+}
 
-	for i in sequence_b.chars() {
-		machine.register_input(i as i64);
+fn gold(program: Vec<i64>, grid: &Grid, video: bool) -> Result<Report, String> {
+	let moves = trace(grid).moves;
+	if moves.is_empty() {
+		return Err("the robot has no path to follow".to_string());
 	}
 
-	for i in sequence_c.chars() {
-		machine.register_input(i as i64);
-	}
+	let routines = match compress(&moves) {
+		Some(routines) => routines,
+		None => {
+			return Err(format!(
+				"the path {} does not fit in {} functions of {} characters",
+				moves.join(","),
+				FUNCTIONS,
+				ROUTINE_LIMIT
+			))
+		}
+	};
 
-	/* register no */
-	machine.register_input('n' as i64);
-	machine.register_input(10);
+	let mut program = program.clone();
+	program[0] = 2;
 
-	loop {
-		let output = machine.run_until_output().unwrap();
-		if output > 255 {
-			return output;
-		}
+	/* drawing the video feed takes about ten times as many steps */
+	let steps = if video { 20_000_000 } else { 1_000_000 };
+	let mut machine = OpMachine::new(program);
+	machine.limits = Limits::default().with_steps(steps);
+	let mut robot = Ascii::new(machine);
+
+	robot.send_line(&routines.main).unwrap();
+	/* the robot asks for all functions, even the ones that are not called */
+	let unused = match routines.functions.first() {
+		Some(function) => function,
+		None => return Err("the path compressed to no functions".to_string()),
+	};
+	for i in 0..FUNCTIONS {
+		robot
+			.send_line(routines.functions.get(i).unwrap_or(unused))
+			.unwrap();
 	}
+
+	robot.send_line(if video { "y" } else { "n" }).unwrap();
+
+	let transcript = robot.read_until_prompt();
+	let dust = match (transcript.state, transcript.values.first()) {
+		(RunState::Fault(error), _) => return Err(format!("the robot did not finish: {}", error)),
+		(_, Some(dust)) => *dust,
+		(_, None) => {
+			return Err(format!(
+				"the robot did not report: {}",
+				transcript.text.trim()
+			))
+		}
+	};
+
+	/* the frames are separated by an empty line, so are the prompts */
+	let frames = if video {
+		transcript
+			.text
+			.split("\n\n")
+			.filter_map(Grid::parse)
+			.collect()
+	} else {
+		Vec::new()
+	};
+
+	Ok(Report { dust, frames })
 }
 
+/**
+ * With --path the scaffold is drawn with the path of the robot,
+ * with --video every frame of the video feed is drawn.
+ */
 fn main() {
-	let mut program: Vec<i64> = include_str!("input")
-		.trim()
-		.split(',')
-		.map(|num| num.parse::<i64>().unwrap())
-		.collect();
+	let args: Vec<String> = env::args().skip(1).collect();
+	let video = args.iter().any(|arg| arg == "--video");
+
+	let program = intcode::parse(include_str!("input"));
+	let grid = scan(program.clone());
 
-	for _ in 0..5000 {
-		program.push(0);
+	if args.iter().any(|arg| arg == "--path") {
+		print!("{}", grid.render(&trace(&grid).cells));
 	}
 
-	let output = silver(program.clone());
+	let output = silver(&grid);
 	println!("Silver: {}", output);
-	let output = gold(program.clone());
-	println!("Gold: {}", output);
+	match gold(program.clone(), &grid, video) {
+		Ok(report) => {
+			for frame in &report.frames {
+				println!("{}", frame);
+			}
+
+			println!("Gold: {}", report.dust);
+		}
+		Err(error) => {
+			eprintln!("Gold: {}", error);
+			process::exit(1);
+		}
+	}
 }
@@ -0,0 +1,93 @@
+use crate::springscript::Script;
+use std::fmt;
+use std::str::FromStr;
+
+/**
+ * A stretch of hull as the droid shows it, # is ground and . a hole.
+ * The droid starts on the first tile, past the last one there is
+ * ground all the way.
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Hull {
+	pub ground: Vec<bool>,
+}
+
+/**
+ * A hull can not hold the character.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NotHull(pub char);
+
+impl fmt::Display for NotHull {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "'{}' is not ground or a hole", self.0)
+	}
+}
+
+impl std::error::Error for NotHull {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+	Crossed,
+	/* the droid fell in the hole at this position */
+	Fell(usize),
+}
+
+impl Hull {
+	pub fn is_ground(&self, x: usize) -> bool {
+		self.ground.get(x).copied().unwrap_or(true)
+	}
+
+	/// What the sensors see from x, A is the tile right after it.
+	pub fn sensors(&self, x: usize, count: usize) -> Vec<bool> {
+		(1..=count).map(|i| self.is_ground(x + i)).collect()
+	}
+
+	/// Walk the droid over the hull with the script, every step it
+	/// either walks one tile or jumps four.
+	pub fn simulate(&self, script: &Script) -> Outcome {
+		let mut x = 0;
+
+		while x < self.ground.len() {
+			if !self.is_ground(x) {
+				return Outcome::Fell(x);
+			}
+
+			x += if script.jumps(&self.sensors(x, script.mode.sensors())) {
+				4
+			} else {
+				1
+			};
+		}
+
+		Outcome::Crossed
+	}
+}
+
+impl FromStr for Hull {
+	type Err = NotHull;
+
+	fn from_str(text: &str) -> Result<Hull, NotHull> {
+		let ground = text
+			.trim()
+			.chars()
+			.map(|ch| match ch {
+				'#' => Ok(true),
+				'.' => Ok(false),
+				ch => Err(NotHull(ch)),
+			})
+			.collect::<Result<Vec<bool>, NotHull>>()?;
+
+		Ok(Hull { ground })
+	}
+}
+
+impl fmt::Display for Hull {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for ground in &self.ground {
+			write!(f, "{}", if *ground { '#' } else { '.' })?;
+		}
+
+		Ok(())
+	}
+}
@@ -1,290 +1,244 @@
-use std::char;
-use std::collections::HashSet;
-use std::collections::VecDeque;
-
-enum OpCodes {
-	Add = 1,
-	Mul,
-	Input,
-	Output,
-	JIT,
-	JIF,
-	LT,
-	EQ,
-	RA,
-	Halt = 99,
-}
+mod hull;
+mod solver;
+mod springscript;
+
+use hull::{Hull, Outcome};
+use intcode::ascii::Ascii;
+use intcode::OpMachine;
+use solver::Solver;
+use springscript::{Expr, Mode, Script};
+use std::env;
+use std::fs;
+use std::process;
 
-enum OpModes {
-	Parametric,
-	Immediate,
-	Relative,
-}
+/**
+ * Let the droid run the script, it reports the hull damage when it
+ * makes it across, otherwise it shows how it fell.
+ */
+fn execute(script: &Script, program: Vec<i64>, debug: bool) -> Result<i64, String> {
+	let mut droid = Ascii::new(OpMachine::new(program));
 
-impl OpCodes {
-	fn from(code: i64) -> OpCodes {
-		match code {
-			1 => OpCodes::Add,
-			2 => OpCodes::Mul,
-			3 => OpCodes::Input,
-			4 => OpCodes::Output,
-			5 => OpCodes::JIT,
-			6 => OpCodes::JIF,
-			7 => OpCodes::LT,
-			8 => OpCodes::EQ,
-			9 => OpCodes::RA,
-			_ => OpCodes::Halt,
-		}
+	for line in script.to_string().lines() {
+		droid.send_line(line).unwrap();
 	}
 
-	fn param_count(&self) -> usize {
-		match self {
-			OpCodes::Add | OpCodes::Mul | OpCodes::LT | OpCodes::EQ => 4,
-			OpCodes::Input | OpCodes::Output | OpCodes::RA => 2,
-			_ => 0,
-		}
+	let transcript = droid.read_until_prompt();
+	if debug {
+		print!("{}", transcript.text);
+	}
+
+	match transcript.values.first() {
+		Some(damage) if *damage > 255 => Ok(*damage),
+		_ => Err(transcript.text),
 	}
 }
 
 /**
- * OpMachine, it handles the operations step by step.
- * It uses the OpCodes struct above.
+ * Learn a script from the hulls the droid falls on, until
+ * it makes it across.
  */
-struct OpMachine {
-	ip: usize,
-	program: Vec<i64>,
-	inputs: VecDeque<i64>,
-	outputs: VecDeque<i64>,
-	base: i64,
-}
-
-impl OpMachine {
-	fn get_value(&self, mode: &OpModes, position: usize) -> i64 {
-		match mode {
-			OpModes::Immediate => self.program[position],
-			OpModes::Relative => self.program[(self.base + self.program[position]) as usize],
-			_ => self.program[self.program[position] as usize],
-		}
-	}
-
-	fn store(&mut self, value: i64, position: usize, mode: &OpModes) {
-		let loc = match mode {
-			OpModes::Relative => (self.base + self.program[position]) as usize,
-			_ => self.program[position] as usize,
+fn solve(program: &[i64], mode: Mode, debug: bool) -> Result<(Script, i64), String> {
+	let mut solver = Solver::new(mode);
+
+	loop {
+		let script = match solver.synthesize() {
+			Some(script) => script,
+			None => {
+				return Err(format!(
+					"no script crosses all {} hulls",
+					solver.hulls.len()
+				))
+			}
 		};
 
-		self.program[loc] = value;
-	}
+		let text = match execute(&script, program.to_vec(), debug) {
+			Ok(damage) => return Ok((script, damage)),
+			Err(text) => text,
+		};
 
-	fn add(&mut self, modes: Vec<OpModes>) {
-		let verb = self.get_value(&modes[0], self.ip + 1);
-		let noun = self.get_value(&modes[1], self.ip + 2);
-		self.store(verb + noun, self.ip + 3, &modes[2]);
+		match solver::fall(&text) {
+			Some(hull) if !solver.hulls.contains(&hull) => solver.hulls.push(hull),
+			Some(hull) => return Err(format!("the droid fell on {} again", hull)),
+			None => return Err(format!("the droid did not fall: {}", text.trim())),
+		}
 	}
+}
 
-	fn mul(&mut self, modes: Vec<OpModes>) {
-		let verb = self.get_value(&modes[0], self.ip + 1);
-		let noun = self.get_value(&modes[1], self.ip + 2);
-		self.store(verb * noun, self.ip + 3, &modes[2]);
+/**
+ * Compile the expression for when to jump.
+ */
+fn jump_when(expr: &str, mode: Mode) -> Script {
+	let expr: Expr = expr.parse().unwrap();
+	match springscript::compile(&expr, mode) {
+		Ok(script) => script,
+		Err(error) => panic!("could not compile {}: {}", expr, error),
 	}
+}
 
-	fn output(&mut self, mode: &OpModes) {
-		let verb = self.get_value(mode, self.ip + 1);
-		self.outputs.push_back(verb);
-	}
+fn silver() -> Script {
+	/* jump when there is a hole in reach, and ground to land on */
+	jump_when("!(A && B && C) && D", Mode::Walk)
+}
 
-	fn input(&mut self, mode: &OpModes) {
-		let value = self.inputs.pop_front().unwrap();
-		self.store(value, self.ip + 1, mode);
-	}
+fn gold() -> Script {
+	/* !E => H, so that after landing there is a step or another jump */
+	jump_when("!(A && B && C) && D && (!E => H)", Mode::Run)
+}
 
-	fn jump_if_true(&mut self, modes: Vec<OpModes>) {
-		let verb = self.get_value(&modes[0], self.ip + 1);
-		let noun = self.get_value(&modes[1], self.ip + 2);
+/**
+ * Without hulls the scripts are learned from the intcode hulls, with
+ * --debug the attempts of the droid are shown. Given hulls like
+ * #####.#..########, the scripts run on them in the simulator instead,
+ * a script to try can be given with --script, or an expression for
+ * when to jump with --expr. With --learned the simulator runs the
+ * learned scripts rather than the ones written by hand. A script
+ * given without hulls runs on the intcode droid.
+ */
+fn main() {
+	let mut args: Vec<String> = env::args().skip(1).collect();
+	let debug = args.iter().any(|arg| arg == "--debug");
+	let learned = args.iter().any(|arg| arg == "--learned");
+	args.retain(|arg| arg != "--debug" && arg != "--learned");
+
+	let mut script = None;
+	if let Some(i) = args.iter().position(|arg| arg == "--script") {
+		let path = args.get(i + 1).expect("--script needs a file");
+		let text = fs::read_to_string(path).expect("could not read the script");
+		script = Some(
+			text.parse::<Script>()
+				.unwrap_or_else(|error| panic!("{}: {}", path, error)),
+		);
+		args.drain(i..i + 2);
+	}
+
+	if let Some(i) = args.iter().position(|arg| arg == "--expr") {
+		let expr: Expr = args
+			.get(i + 1)
+			.expect("--expr needs an expression")
+			.parse()
+			.unwrap_or_else(|error| panic!("{}", error));
+		/* WALK unless the expression needs the sensors of RUN */
+		let compiled = springscript::compile(&expr, Mode::Walk)
+			.or_else(|_| springscript::compile(&expr, Mode::Run));
+		script =
+			Some(compiled.unwrap_or_else(|error| panic!("could not compile {}: {}", expr, error)));
+		args.drain(i..i + 2);
+	}
+
+	let program = intcode::parse(include_str!("input"));
+
+	if !args.is_empty() {
+		let scripts = match script {
+			Some(script) => vec![script],
+			None if learned => [Mode::Walk, Mode::Run]
+				.iter()
+				.map(|mode| match solve(&program, *mode, debug) {
+					Ok((script, _)) => script,
+					Err(error) => {
+						eprintln!("{}: {}", mode, error);
+						process::exit(1);
+					}
+				})
+				.collect(),
+			None => vec![silver(), gold()],
+		};
 
-		if verb != 0 {
-			self.ip = noun as usize;
-		} else {
-			self.ip += 3;
+		for hull in &args {
+			let hull: Hull = hull.parse().unwrap_or_else(|error| panic!("{}", error));
+			for script in &scripts {
+				match hull.simulate(script) {
+					Outcome::Crossed => println!("{} {}: crossed", script.mode, hull),
+					Outcome::Fell(x) => println!("{} {}: fell at {}", script.mode, hull, x),
+				}
+			}
 		}
-	}
-
-	fn register_input(&mut self, input: i64) {
-		self.inputs.push_back(input);
-	}
 
-	fn has_output(&self) -> bool {
-		return !self.outputs.is_empty();
+		return;
 	}
 
-	fn jump_if_false(&mut self, modes: Vec<OpModes>) {
-		let verb = self.get_value(&modes[0], self.ip + 1);
-		let noun = self.get_value(&modes[1], self.ip + 2);
-		if verb == 0 {
-			self.ip = noun as usize;
-		} else {
-			self.ip += 3;
+	if let Some(script) = script {
+		match execute(&script, program, debug) {
+			Ok(damage) => println!("{}: {}", script.mode, damage),
+			Err(text) => {
+				eprint!("{}", text);
+				process::exit(1);
+			}
 		}
-	}
-
-	fn less_than(&mut self, modes: Vec<OpModes>) {
-		let verb = self.get_value(&modes[0], self.ip + 1);
-		let noun = self.get_value(&modes[1], self.ip + 2);
 
-		if verb < noun {
-			self.store(1, self.ip + 3, &modes[2]);
-		} else {
-			self.store(0, self.ip + 3, &modes[2]);
-		}
+		return;
 	}
 
-	fn equal(&mut self, modes: Vec<OpModes>) {
-		let verb = self.get_value(&modes[0], self.ip + 1);
-		let noun = self.get_value(&modes[1], self.ip + 2);
-		if verb == noun {
-			self.store(1, self.ip + 3, &modes[2]);
-		} else {
-			self.store(0, self.ip + 3, &modes[2]);
+	for (name, mode) in [("Silver", Mode::Walk), ("Gold", Mode::Run)] {
+		match solve(&program, mode, debug) {
+			Ok((_, damage)) => println!("{}: {}", name, damage),
+			Err(error) => {
+				eprintln!("{}: {}", name, error);
+				process::exit(1);
+			}
 		}
 	}
+}
 
-	fn relative_add(&mut self, modes: Vec<OpModes>) {
-		let verb = self.get_value(&modes[0], self.ip + 1);
-		self.base += verb;
-	}
+#[cfg(test)]
+mod tests {
+	use super::*;
 
-	fn get_mode(&self, input: i64) -> OpModes {
-		if input % 10 == 1 {
-			return OpModes::Immediate;
-		} else if input % 10 == 2 {
-			return OpModes::Relative;
+	fn eval(expr: &Expr, sensors: &[bool]) -> bool {
+		match expr {
+			Expr::Sensor(sensor) => sensors[*sensor],
+			Expr::Not(a) => !eval(a, sensors),
+			Expr::And(a, b) => eval(a, sensors) && eval(b, sensors),
+			Expr::Or(a, b) => eval(a, sensors) || eval(b, sensors),
 		}
-
-		OpModes::Parametric
 	}
 
-	fn step(&mut self) -> bool {
-		let opcode = self.program[self.ip];
-		let code = OpCodes::from(opcode % 100);
-		let modes = vec![
-			self.get_mode(opcode / 100),
-			self.get_mode(opcode / 1000),
-			self.get_mode(opcode / 10000),
-		];
-
-		match code {
-			OpCodes::Add => self.add(modes),
-			OpCodes::Mul => self.mul(modes),
-			OpCodes::Input => self.input(&modes[0]),
-			OpCodes::Output => self.output(&modes[0]),
-			OpCodes::JIT => self.jump_if_true(modes),
-			OpCodes::JIF => self.jump_if_false(modes),
-			OpCodes::LT => self.less_than(modes),
-			OpCodes::EQ => self.equal(modes),
-			OpCodes::RA => self.relative_add(modes),
-			OpCodes::Halt => return false,
-		}
-
-		self.ip += code.param_count();
-		true
+	fn simulate(script: &Script, hull: &str) -> Outcome {
+		hull.parse::<Hull>().unwrap().simulate(script)
 	}
 
-	fn run_until_output(&mut self) -> Option<i64> {
-		while self.step() {
-			if self.has_output() {
-				return Some(self.outputs.pop_front().unwrap());
-			}
-		}
-
-		return None;
-	}
+	#[test]
+	fn hulls() {
+		assert_eq!(simulate(&silver(), "#####.#..########"), Outcome::Crossed);
+		assert_eq!(simulate(&silver(), "#####.#.##..#.####"), Outcome::Fell(7));
+		assert_eq!(simulate(&silver(), "#####.#.##.##.###"), Outcome::Fell(7));
+		assert_eq!(simulate(&gold(), "#####.#.##.##.###"), Outcome::Crossed);
 
-	fn run(&mut self) {
-		let mut result = self.run_until_output();
-		while result.is_some() {
-			println!("{}", result.unwrap());
-			result = self.run_until_output();
-		}
+		let script: Script = "NOT A J\nWALK\n".parse().unwrap();
+		assert_eq!(simulate(&script, "#####..#.########"), Outcome::Fell(8));
 	}
-}
 
-fn execute(instructions: Vec<&str>, program: Vec<i64>, debug: bool) -> i64 {
-	let mut machine = OpMachine {
-		program: program,
-		inputs: VecDeque::new(),
-		outputs: VecDeque::new(),
-		ip: 0,
-		base: 0,
-	};
-
-	for instruction in instructions {
-		for ch in instruction.chars() {
-			machine.register_input(ch as i64);
-		}
+	#[test]
+	fn script_round_trip() {
+		let text = "NOT A J\nNOT B T\nOR T J\nNOT C T\nOR T J\nAND D J\nWALK\n";
+		let script: Script = text.parse().unwrap();
 
-		machine.register_input('\n' as i64);
+		assert_eq!(script.to_string(), text);
+		assert_eq!(gold().to_string().parse::<Script>().unwrap(), gold());
 	}
 
-	let mut result = machine.run_until_output();
-	while result.is_some() {
-		let mut output = result.unwrap();
+	#[test]
+	fn compile_matches_expr() {
+		let exprs = [
+			("!(A && B && C) && D", Mode::Walk),
+			("A || !B", Mode::Walk),
+			("!(A || B) && (C || !D)", Mode::Walk),
+			("!(A && B && C) && D && (!E => H)", Mode::Run),
+		];
 
-		if output <= 255 && debug {
-			print!("{}", char::from_u32(result.unwrap() as u32).unwrap());
-		} else if output > 255 {
-			return output;
+		for (text, mode) in &exprs {
+			let expr: Expr = text.parse().unwrap();
+			let script = springscript::compile(&expr, *mode).unwrap();
+
+			for bits in 0..1 << mode.sensors() {
+				let sensors: Vec<bool> = (0..mode.sensors()).map(|i| bits >> i & 1 == 1).collect();
+				assert_eq!(
+					script.jumps(&sensors),
+					eval(&expr, &sensors),
+					"{} on {:?}",
+					text,
+					sensors
+				);
+			}
 		}
-		result = machine.run_until_output();
-	}
-
-	0
-}
-
-fn silver(program: Vec<i64>) -> i64 {
-	/* not(A && B && C) && D */
-	let instructions = vec![
-		"NOT J J", /* make sure the register is true. */
-		"AND A J", /* A */
-		"AND B J", /* A && B */
-		"AND C J", /* A && C */
-		"NOT J J", /* !(A && B && C) */
-		"AND D J", /* !(A && B && C) & D */
-		"WALK",
-	];
-
-	execute(instructions, program, false)
-}
-
-fn gold(program: Vec<i64>) -> i64 {
-	/* not(A && B && C) && D && (!E => H), !E => H, is equal to E || H  */
-	let instructions = vec![
-		"NOT J J", /* make sure the register is true. */
-		"AND A J", /* A */
-		"AND B J", /* A && B */
-		"AND C J", /* A && C */
-		"NOT J J", /* !(A && B && C) */
-		"AND D J", /* !(A && B && C) & D */
-		"NOT E T", /* !E */
-		"NOT T T", /* E */
-		"OR H T",  /* E || H */
-		"AND T J", /* not(A && B && C) && D &&(!E => H) */
-		"RUN",
-	];
-
-	execute(instructions, program, false)
-}
-
-fn main() {
-	let mut program: Vec<i64> = include_str!("input")
-		.trim()
-		.split(',')
-		.map(|num| num.parse::<i64>().unwrap())
-		.collect();
-
-	for _ in 0..1000 {
-		program.push(0);
 	}
-
-	println!("Silver: {}", silver(program.clone()));
-	println!("Gold: {}", gold(program.clone()));
 }
@@ -0,0 +1,254 @@
+use crate::hull::{Hull, Outcome};
+use crate::springscript::{self, Expr, Instruction, Mode, Op, Register, Script};
+use std::collections::{HashMap, HashSet};
+
+/* the readings of the sensors at a step, a bit per sensor with A the lowest */
+type Pattern = usize;
+
+/* a product term, the bits of free do not matter, the others are value */
+type Term = (Pattern, Pattern);
+
+/* how many jump tables are tried before giving up */
+const MAX_TABLES: usize = 10_000;
+
+fn pattern(sensors: &[bool]) -> Pattern {
+	sensors
+		.iter()
+		.enumerate()
+		.filter(|(_, ground)| **ground)
+		.map(|(i, _)| 1 << i)
+		.sum()
+}
+
+enum Walk {
+	Crossed,
+	Fell,
+	/* the table does not say what to do with the pattern yet */
+	Unknown(Pattern),
+}
+
+/**
+ * Learns when to jump from the hulls the droid fell on. A table of
+ * which sensor patterns to jump at is searched for, such that the
+ * droid crosses every hull, the table is minimized to a boolean
+ * expression and compiled to springscript.
+ */
+pub struct Solver {
+	pub mode: Mode,
+	pub hulls: Vec<Hull>,
+}
+
+impl Solver {
+	pub fn new(mode: Mode) -> Solver {
+		Solver {
+			mode,
+			hulls: Vec::new(),
+		}
+	}
+
+	fn walk(&self, hull: &Hull, table: &HashMap<Pattern, bool>) -> Walk {
+		let mut x = 0;
+
+		while x < hull.ground.len() {
+			if !hull.is_ground(x) {
+				return Walk::Fell;
+			}
+
+			let pattern = pattern(&hull.sensors(x, self.mode.sensors()));
+			x += match table.get(&pattern) {
+				Some(true) => 4,
+				Some(false) => 1,
+				None => return Walk::Unknown(pattern),
+			};
+		}
+
+		Walk::Crossed
+	}
+
+	/// Depth first search for the tables that cross all hulls,
+	/// walking is tried before jumping. Stops at the first table
+	/// that gives a script, or when tries runs out.
+	fn search(&self, table: &mut HashMap<Pattern, bool>, tries: &mut usize) -> Option<Script> {
+		let mut unknown = None;
+		for hull in &self.hulls {
+			match self.walk(hull, table) {
+				Walk::Crossed => {}
+				Walk::Fell => return None,
+				Walk::Unknown(pattern) => {
+					unknown = Some(pattern);
+					break;
+				}
+			}
+		}
+
+		let pattern = match unknown {
+			Some(pattern) => pattern,
+			None => {
+				*tries += 1;
+				return self.script(table);
+			}
+		};
+
+		for jump in [false, true] {
+			if *tries >= MAX_TABLES {
+				break;
+			}
+
+			table.insert(pattern, jump);
+			if let Some(script) = self.search(table, tries) {
+				return Some(script);
+			}
+		}
+
+		table.remove(&pattern);
+		None
+	}
+
+	/// The shortest script for the table, from the jumps or from the
+	/// walks negated, when it fits the droid and crosses every hull.
+	fn script(&self, table: &HashMap<Pattern, bool>) -> Option<Script> {
+		let sensors = self.mode.sensors();
+		let jumps: Vec<Pattern> = table
+			.iter()
+			.filter(|(_, jump)| **jump)
+			.map(|(p, _)| *p)
+			.collect();
+		let walks: Vec<Pattern> = table
+			.iter()
+			.filter(|(_, jump)| !**jump)
+			.map(|(p, _)| *p)
+			.collect();
+
+		let candidates = if jumps.is_empty() {
+			/* J starts out false */
+			vec![Script {
+				instructions: Vec::new(),
+				mode: self.mode,
+			}]
+		} else if walks.is_empty() {
+			/* T starts out false */
+			vec![Script {
+				instructions: vec![Instruction {
+					op: Op::Not,
+					x: Register::T,
+					y: Register::J,
+				}],
+				mode: self.mode,
+			}]
+		} else {
+			let jump = expr(&minimize(&jumps, &walks, sensors), sensors);
+			let walk = Expr::Not(Box::new(expr(&minimize(&walks, &jumps, sensors), sensors)));
+
+			[jump, walk]
+				.iter()
+				.filter_map(|expr| springscript::compile(expr, self.mode).ok())
+				.collect()
+		};
+
+		candidates
+			.into_iter()
+			.filter(|script| {
+				self.hulls
+					.iter()
+					.all(|hull| hull.simulate(script) == Outcome::Crossed)
+			})
+			.min_by_key(|script| script.instructions.len())
+	}
+
+	/// A script that crosses every hull seen so far, None when there
+	/// is none that fits in springscript.
+	pub fn synthesize(&self) -> Option<Script> {
+		self.search(&mut HashMap::new(), &mut 0)
+	}
+}
+
+/**
+ * Minimize the function that is true on the patterns of on and false
+ * on the ones of off, with Quine-McCluskey. All other patterns are
+ * free to be either, which is where the minimizing comes from. The
+ * prime implicants are picked greedily, the one that covers the most
+ * first.
+ */
+fn minimize(on: &[Pattern], off: &[Pattern], sensors: usize) -> Vec<Term> {
+	let off: HashSet<Pattern> = off.iter().copied().collect();
+	let mut current: HashSet<Term> = (0..1 << sensors)
+		.filter(|p| !off.contains(p))
+		.map(|p| (p, 0))
+		.collect();
+	let mut primes = Vec::new();
+
+	while !current.is_empty() {
+		let mut next = HashSet::new();
+		let mut merged = HashSet::new();
+
+		for (value, free) in &current {
+			for bit in (0..sensors).map(|i| 1 << i).filter(|bit| free & bit == 0) {
+				let other = (value ^ bit, *free);
+				if current.contains(&other) {
+					next.insert((value & !bit, free | bit));
+					merged.insert((*value, *free));
+					merged.insert(other);
+				}
+			}
+		}
+
+		primes.extend(current.difference(&merged).copied());
+		current = next;
+	}
+
+	let covers = |(value, free): &Term, pattern: &Pattern| pattern & !free == *value;
+	let mut uncovered: Vec<Pattern> = on.to_vec();
+	let mut terms = Vec::new();
+
+	while !uncovered.is_empty() {
+		let best = primes
+			.iter()
+			.max_by_key(|term| {
+				let covered = uncovered
+					.iter()
+					.filter(|pattern| covers(term, pattern))
+					.count();
+				(covered, term.1.count_ones(), std::cmp::Reverse(*term))
+			})
+			.copied()
+			.unwrap();
+
+		uncovered.retain(|pattern| !covers(&best, pattern));
+		terms.push(best);
+	}
+
+	terms
+}
+
+/**
+ * The sum of the product terms as an expression, there has to be at
+ * least one term, and every term needs a sensor that matters.
+ */
+fn expr(terms: &[Term], sensors: usize) -> Expr {
+	let product = |(value, free): &Term| {
+		let mut literals =
+			(0..sensors)
+				.filter(|i| (free >> i) & 1 == 0)
+				.map(|i| match (value >> i) & 1 {
+					1 => Expr::Sensor(i),
+					_ => Expr::Not(Box::new(Expr::Sensor(i))),
+				});
+
+		let first = literals.next().unwrap();
+		literals.fold(first, |a, b| Expr::And(Box::new(a), Box::new(b)))
+	};
+
+	let mut products = terms.iter().map(product);
+	let first = products.next().unwrap();
+	products.fold(first, |a, b| Expr::Or(Box::new(a), Box::new(b)))
+}
+
+/**
+ * The hull the droid fell on, from the animation it shows after
+ * falling: the first line of only ground and holes.
+ */
+pub fn fall(text: &str) -> Option<Hull> {
+	text.lines()
+		.find(|line| line.contains('#') && line.chars().all(|ch| ch == '#' || ch == '.'))
+		.and_then(|line| line.parse().ok())
+}
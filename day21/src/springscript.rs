@@ -0,0 +1,574 @@
+use std::fmt;
+use std::str::FromStr;
+
+/* the droid does not take longer programs */
+pub const MAX_INSTRUCTIONS: usize = 15;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+	Walk,
+	Run,
+}
+
+impl Mode {
+	/// The number of sensors, WALK only has A to D.
+	pub fn sensors(&self) -> usize {
+		match self {
+			Mode::Walk => 4,
+			Mode::Run => 9,
+		}
+	}
+}
+
+impl fmt::Display for Mode {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Mode::Walk => write!(f, "WALK"),
+			Mode::Run => write!(f, "RUN"),
+		}
+	}
+}
+
+/**
+ * A sensor, A is 0, or one of the writable registers.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Register {
+	Sensor(usize),
+	T,
+	J,
+}
+
+impl fmt::Display for Register {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Register::Sensor(sensor) => write!(f, "{}", (b'A' + *sensor as u8) as char),
+			Register::T => write!(f, "T"),
+			Register::J => write!(f, "J"),
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+	And,
+	Or,
+	Not,
+}
+
+/**
+ * Op x y, which writes its result to y.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Instruction {
+	pub op: Op,
+	pub x: Register,
+	pub y: Register,
+}
+
+impl fmt::Display for Instruction {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let op = match self.op {
+			Op::And => "AND",
+			Op::Or => "OR",
+			Op::Not => "NOT",
+		};
+
+		write!(f, "{} {} {}", op, self.x, self.y)
+	}
+}
+
+/**
+ * A springscript program, it prints as the lines to send to the droid.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Script {
+	pub instructions: Vec<Instruction>,
+	pub mode: Mode,
+}
+
+impl fmt::Display for Script {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for instruction in &self.instructions {
+			writeln!(f, "{}", instruction)?;
+		}
+
+		writeln!(f, "{}", self.mode)
+	}
+}
+
+impl Script {
+	/// Run the script on the sensor readings, A first, and tell
+	/// whether the droid jumps. T and J are false on every run.
+	pub fn jumps(&self, sensors: &[bool]) -> bool {
+		let mut t = false;
+		let mut j = false;
+
+		for instruction in &self.instructions {
+			let x = match instruction.x {
+				Register::Sensor(sensor) => sensors[sensor],
+				Register::T => t,
+				Register::J => j,
+			};
+
+			let y = match instruction.y {
+				Register::T => &mut t,
+				_ => &mut j,
+			};
+
+			*y = match instruction.op {
+				Op::And => x && *y,
+				Op::Or => x || *y,
+				Op::Not => !x,
+			};
+		}
+
+		j
+	}
+}
+
+fn register(name: &str) -> Option<Register> {
+	match name.as_bytes() {
+		[b'T'] => Some(Register::T),
+		[b'J'] => Some(Register::J),
+		[sensor @ b'A'..=b'I'] => Some(Register::Sensor((sensor - b'A') as usize)),
+		_ => None,
+	}
+}
+
+/**
+ * Parse a script as it would be sent to the droid, an instruction
+ * per line and WALK or RUN at the end. It is held to the same rules
+ * as the droid: the sensors of the mode, and the instruction limit.
+ */
+impl FromStr for Script {
+	type Err = Error;
+
+	fn from_str(text: &str) -> Result<Script, Error> {
+		let mut instructions = Vec::new();
+		let lines: Vec<&str> = text
+			.lines()
+			.map(str::trim)
+			.filter(|line| !line.is_empty())
+			.collect();
+
+		for (i, line) in lines.iter().enumerate() {
+			let mode = match *line {
+				"WALK" => Some(Mode::Walk),
+				"RUN" => Some(Mode::Run),
+				_ => None,
+			};
+
+			if let Some(mode) = mode {
+				if i + 1 < lines.len() {
+					return Err(Error::Parse(
+						i + 1,
+						format!("{} should be the last line", mode),
+					));
+				}
+
+				if let Some(sensor) =
+					instructions
+						.iter()
+						.find_map(|instruction: &Instruction| match instruction.x {
+							Register::Sensor(sensor) if sensor >= mode.sensors() => Some(sensor),
+							_ => None,
+						}) {
+					let sensor = Register::Sensor(sensor).to_string();
+					return Err(Error::Sensor(sensor.chars().next().unwrap(), mode));
+				}
+
+				if instructions.len() > MAX_INSTRUCTIONS {
+					return Err(Error::TooLong(instructions.len()));
+				}
+
+				return Ok(Script { instructions, mode });
+			}
+
+			let words: Vec<&str> = line.split_whitespace().collect();
+			let op = match words[0] {
+				"AND" => Op::And,
+				"OR" => Op::Or,
+				"NOT" => Op::Not,
+				op => return Err(Error::Parse(i + 1, format!("unknown instruction {}", op))),
+			};
+
+			let (x, y) = match words[1..] {
+				[x, y] => (register(x), register(y)),
+				_ => return Err(Error::Parse(i + 1, "expected two registers".to_string())),
+			};
+
+			match (x, y) {
+				(Some(x), Some(y @ (Register::T | Register::J))) => {
+					instructions.push(Instruction { op, x, y })
+				}
+				_ => return Err(Error::Parse(i + 1, "invalid registers".to_string())),
+			}
+		}
+
+		Err(Error::Parse(
+			lines.len(),
+			"expected WALK or RUN".to_string(),
+		))
+	}
+}
+
+/**
+ * A boolean expression over the sensors.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Expr {
+	Sensor(usize),
+	Not(Box<Expr>),
+	And(Box<Expr>, Box<Expr>),
+	Or(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+	fn sensors(&self) -> usize {
+		match self {
+			Expr::Sensor(sensor) => sensor + 1,
+			Expr::Not(a) => a.sensors(),
+			Expr::And(a, b) | Expr::Or(a, b) => a.sensors().max(b.sensors()),
+		}
+	}
+
+	/// The sensor and whether it is negated, when this is one.
+	fn literal(&self) -> Option<(usize, bool)> {
+		match self {
+			Expr::Sensor(sensor) => Some((*sensor, false)),
+			Expr::Not(a) => a.literal().map(|(sensor, negated)| (sensor, !negated)),
+			_ => None,
+		}
+	}
+}
+
+impl fmt::Display for Expr {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Expr::Sensor(sensor) => write!(f, "{}", Register::Sensor(*sensor)),
+			Expr::Not(a) => match **a {
+				Expr::Sensor(_) | Expr::Not(_) => write!(f, "!{}", a),
+				_ => write!(f, "!({})", a),
+			},
+			Expr::And(a, b) => {
+				for (i, side) in [a, b].iter().enumerate() {
+					if i > 0 {
+						write!(f, " && ")?;
+					}
+
+					match ***side {
+						Expr::Or(_, _) => write!(f, "({})", side)?,
+						_ => write!(f, "{}", side)?,
+					}
+				}
+
+				Ok(())
+			}
+			Expr::Or(a, b) => write!(f, "{} || {}", a, b),
+		}
+	}
+}
+
+/**
+ * Why an expression could not be parsed or compiled.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+	/* where in the text, a character of an expression or a line of a script */
+	Parse(usize, String),
+	/* the sensor is out of reach in the mode */
+	Sensor(char, Mode),
+	/* the expression needs more than T and J */
+	Registers(String),
+	/* the number of instructions it compiled to */
+	TooLong(usize),
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Error::Parse(position, message) => write!(f, "at {}: {}", position, message),
+			Error::Sensor(sensor, mode) => {
+				write!(f, "sensor {} is not available in {}", sensor, mode)
+			}
+			Error::Registers(expr) => write!(f, "{} needs more registers than T and J", expr),
+			Error::TooLong(count) => write!(
+				f,
+				"{} instructions are needed, at most {} fit",
+				count, MAX_INSTRUCTIONS
+			),
+		}
+	}
+}
+
+impl std::error::Error for Error {}
+
+/**
+ * Recursive descent over the expression text, from the loosest
+ * binding operator to the tightest: =>, ||, && and !. The words
+ * not, and, or and the single & and | are accepted as well.
+ */
+struct Parser<'a> {
+	text: &'a [u8],
+	position: usize,
+}
+
+impl<'a> Parser<'a> {
+	fn error<T>(&self, message: &str) -> Result<T, Error> {
+		Err(Error::Parse(self.position, message.to_string()))
+	}
+
+	fn skip_spaces(&mut self) {
+		while self.position < self.text.len() && self.text[self.position].is_ascii_whitespace() {
+			self.position += 1;
+		}
+	}
+
+	/// Consume the first of the tokens that comes next, words only
+	/// match when they are not the start of a longer word.
+	fn eat(&mut self, tokens: &[&str]) -> bool {
+		self.skip_spaces();
+
+		for token in tokens {
+			let end = self.position + token.len();
+			if end > self.text.len()
+				|| !self.text[self.position..end].eq_ignore_ascii_case(token.as_bytes())
+			{
+				continue;
+			}
+
+			let word = token.as_bytes()[0].is_ascii_alphabetic();
+			if word
+				&& self
+					.text
+					.get(end)
+					.is_some_and(|ch| ch.is_ascii_alphanumeric())
+			{
+				continue;
+			}
+
+			self.position = end;
+			return true;
+		}
+
+		false
+	}
+
+	fn implication(&mut self) -> Result<Expr, Error> {
+		let a = self.or()?;
+		if self.eat(&["=>"]) {
+			let b = self.implication()?;
+			return Ok(Expr::Or(Box::new(Expr::Not(Box::new(a))), Box::new(b)));
+		}
+
+		Ok(a)
+	}
+
+	fn or(&mut self) -> Result<Expr, Error> {
+		let mut a = self.and()?;
+		while self.eat(&["||", "|", "or"]) {
+			a = Expr::Or(Box::new(a), Box::new(self.and()?));
+		}
+
+		Ok(a)
+	}
+
+	fn and(&mut self) -> Result<Expr, Error> {
+		let mut a = self.not()?;
+		while self.eat(&["&&", "&", "and"]) {
+			a = Expr::And(Box::new(a), Box::new(self.not()?));
+		}
+
+		Ok(a)
+	}
+
+	fn not(&mut self) -> Result<Expr, Error> {
+		if self.eat(&["!", "not"]) {
+			return Ok(Expr::Not(Box::new(self.not()?)));
+		}
+
+		if self.eat(&["("]) {
+			let a = self.implication()?;
+			if !self.eat(&[")"]) {
+				return self.error("expected )");
+			}
+
+			return Ok(a);
+		}
+
+		self.skip_spaces();
+		let sensor = match self.text.get(self.position) {
+			Some(ch) => ch.to_ascii_uppercase(),
+			None => return self.error("expected a sensor"),
+		};
+
+		let next = self.text.get(self.position + 1);
+		if !(b'A'..=b'I').contains(&sensor) || next.is_some_and(|ch| ch.is_ascii_alphanumeric()) {
+			return self.error("expected a sensor A to I");
+		}
+
+		self.position += 1;
+		Ok(Expr::Sensor((sensor - b'A') as usize))
+	}
+}
+
+impl FromStr for Expr {
+	type Err = Error;
+
+	fn from_str(text: &str) -> Result<Expr, Error> {
+		let mut parser = Parser {
+			text: text.as_bytes(),
+			position: 0,
+		};
+
+		let expr = parser.implication()?;
+		parser.skip_spaces();
+		if parser.position < text.len() {
+			return parser.error("expected the end of the expression");
+		}
+
+		Ok(expr)
+	}
+}
+
+/* T and J, whether they still hold the false they start with */
+type Fresh = [bool; 2];
+
+fn slot(register: Register) -> usize {
+	match register {
+		Register::T => 0,
+		_ => 1,
+	}
+}
+
+fn instruction(op: Op, x: Register, y: Register) -> Instruction {
+	Instruction { op, x, y }
+}
+
+/**
+ * Compile the expression into the register, with the help of the
+ * scratch register when there is one. The register ends up holding
+ * either the value of the expression, or its negation when the
+ * returned flag is set, that way a not is free until the value is
+ * combined with another one. Both orders of the operands are tried,
+ * the shortest code wins.
+ */
+fn generate(
+	expr: &Expr,
+	register: Register,
+	scratch: Option<Register>,
+	fresh: Fresh,
+) -> Result<(Vec<Instruction>, bool), Error> {
+	if let Some((sensor, negated)) = expr.literal() {
+		let sensor = Register::Sensor(sensor);
+
+		/* OR into a false register loads the sensor as it is */
+		return Ok(match (fresh[slot(register)], negated) {
+			(true, false) => (vec![instruction(Op::Or, sensor, register)], false),
+			(true, true) => (vec![instruction(Op::Not, sensor, register)], false),
+			(false, negated) => (vec![instruction(Op::Not, sensor, register)], !negated),
+		});
+	}
+
+	let (op, a, b) = match expr {
+		Expr::Not(a) => {
+			let (code, negated) = generate(a, register, scratch, fresh)?;
+			return Ok((code, !negated));
+		}
+		Expr::And(a, b) => (Op::And, a, b),
+		Expr::Or(a, b) => (Op::Or, a, b),
+		Expr::Sensor(_) => unreachable!(),
+	};
+
+	/* a literal always goes second, it can be used without a register */
+	let mut orders = vec![(a, b)];
+	if a.literal().is_some() == b.literal().is_some() {
+		orders.push((b, a));
+	} else if a.literal().is_some() {
+		orders = vec![(b, a)];
+	}
+
+	let mut best: Option<(Vec<Instruction>, bool)> = None;
+	let mut error = None;
+	for (first, second) in orders {
+		match combine(op, first, second, register, scratch, fresh) {
+			Ok(code) if best.as_ref().is_none_or(|best| code.0.len() < best.0.len()) => {
+				best = Some(code)
+			}
+			Ok(_) => {}
+			Err(e) => error = Some(e),
+		}
+	}
+
+	match best {
+		Some(best) => Ok(best),
+		None => Err(error.unwrap()),
+	}
+}
+
+fn combine(
+	op: Op,
+	first: &Expr,
+	second: &Expr,
+	register: Register,
+	scratch: Option<Register>,
+	mut fresh: Fresh,
+) -> Result<(Vec<Instruction>, bool), Error> {
+	let (mut code, mut negated) = generate(first, register, scratch, fresh)?;
+	for instruction in &code {
+		fresh[slot(instruction.y)] = false;
+	}
+
+	let (operand, operand_negated) = match second.literal() {
+		Some((sensor, negated)) => (Register::Sensor(sensor), negated),
+		None => {
+			let scratch = match scratch {
+				Some(scratch) => scratch,
+				None => return Err(Error::Registers(second.to_string())),
+			};
+
+			let (more, negated) = generate(second, scratch, None, fresh)?;
+			code.extend(more);
+			(scratch, negated)
+		}
+	};
+
+	if negated != operand_negated {
+		code.push(instruction(Op::Not, register, register));
+		negated = !negated;
+	}
+
+	/* both are negated, by De Morgan the other op gives the negated result */
+	let op = match (op, negated) {
+		(op, false) => op,
+		(Op::And, true) => Op::Or,
+		(_, true) => Op::And,
+	};
+
+	code.push(instruction(op, operand, register));
+	Ok((code, negated))
+}
+
+/**
+ * Compile the expression to a script that jumps when it is true, it
+ * fails when a sensor is out of reach of the mode, when it needs more
+ * than the two registers, or more than the droid's instruction limit.
+ */
+pub fn compile(expr: &Expr, mode: Mode) -> Result<Script, Error> {
+	if expr.sensors() > mode.sensors() {
+		let sensor = Register::Sensor(expr.sensors() - 1).to_string();
+		return Err(Error::Sensor(sensor.chars().next().unwrap(), mode));
+	}
+
+	let (mut instructions, negated) = generate(expr, Register::J, Some(Register::T), [true, true])?;
+	if negated {
+		instructions.push(instruction(Op::Not, Register::J, Register::J));
+	}
+
+	if instructions.len() > MAX_INSTRUCTIONS {
+		return Err(Error::TooLong(instructions.len()));
+	}
+
+	Ok(Script { instructions, mode })
+}
@@ -1,232 +1,65 @@
-use std::char;
-use std::collections::HashSet;
-use std::collections::VecDeque;
+use intcode::ascii::Ascii;
+use intcode::{OpMachine, RunState, Snapshot};
+use std::env;
 use std::io;
+use std::process;
 
-enum OpCodes {
-	Add = 1,
-	Mul,
-	Input,
-	Output,
-	JIT,
-	JIF,
-	LT,
-	EQ,
-	RA,
-	Halt = 99,
-}
-
-enum OpModes {
-	Parametric,
-	Immediate,
-	Relative,
-}
-
-impl OpCodes {
-	fn from(code: i64) -> OpCodes {
-		match code {
-			1 => OpCodes::Add,
-			2 => OpCodes::Mul,
-			3 => OpCodes::Input,
-			4 => OpCodes::Output,
-			5 => OpCodes::JIT,
-			6 => OpCodes::JIF,
-			7 => OpCodes::LT,
-			8 => OpCodes::EQ,
-			9 => OpCodes::RA,
-			_ => OpCodes::Halt,
-		}
-	}
-
-	fn param_count(&self) -> usize {
-		match self {
-			OpCodes::Add | OpCodes::Mul | OpCodes::LT | OpCodes::EQ => 4,
-			OpCodes::Input | OpCodes::Output | OpCodes::RA => 2,
-			_ => 0,
-		}
-	}
+/**
+ * Save the game, the text the droid showed last is kept as the
+ * outputs of the snapshot, so it can be shown again on a load.
+ */
+fn save(machine: &OpMachine, last: &str, path: &str) -> io::Result<()> {
+	let mut snapshot = machine.snapshot();
+	snapshot.outputs = last.chars().map(|ch| ch as i64).collect();
+	snapshot.save(path)
 }
 
 /**
- * OpMachine, it handles the operations step by step.
- * It uses the OpCodes struct above.
+ * Load a saved game, returns the text the droid showed when it was saved.
  */
-struct OpMachine {
-	ip: usize,
-	program: Vec<i64>,
-	inputs: VecDeque<i64>,
-	outputs: VecDeque<i64>,
-	base: i64,
-	exit_code: u32,
+fn load(machine: &mut OpMachine, path: &str) -> io::Result<String> {
+	machine.restore(&Snapshot::load(path)?);
+	Ok(machine
+		.outputs
+		.drain(..)
+		.map(|value| value as u8 as char)
+		.collect())
 }
 
-impl OpMachine {
-	fn get_value(&self, mode: &OpModes, position: usize) -> i64 {
-		match mode {
-			OpModes::Immediate => self.program[position],
-			OpModes::Relative => self.program[(self.base + self.program[position]) as usize],
-			_ => self.program[self.program[position] as usize],
-		}
-	}
-
-	fn store(&mut self, value: i64, position: usize, mode: &OpModes) {
-		let loc = match mode {
-			OpModes::Relative => (self.base + self.program[position]) as usize,
-			_ => self.program[position] as usize,
-		};
-
-		self.program[loc] = value;
-	}
-
-	fn add(&mut self, modes: Vec<OpModes>) {
-		let verb = self.get_value(&modes[0], self.ip + 1);
-		let noun = self.get_value(&modes[1], self.ip + 2);
-		self.store(verb + noun, self.ip + 3, &modes[2]);
-	}
-
-	fn mul(&mut self, modes: Vec<OpModes>) {
-		let verb = self.get_value(&modes[0], self.ip + 1);
-		let noun = self.get_value(&modes[1], self.ip + 2);
-		self.store(verb * noun, self.ip + 3, &modes[2]);
-	}
-
-	fn output(&mut self, mode: &OpModes) {
-		let verb = self.get_value(mode, self.ip + 1);
-		self.outputs.push_back(verb);
-	}
-
-	fn input(&mut self, mode: &OpModes) {
-		if self.inputs.len() == 0 {
-			self.exit_code = 2;
-			return;
-		}
-
-		let value = self.inputs.pop_front().unwrap();
-		self.store(value, self.ip + 1, mode);
-	}
-
-	fn jump_if_true(&mut self, modes: Vec<OpModes>) {
-		let verb = self.get_value(&modes[0], self.ip + 1);
-		let noun = self.get_value(&modes[1], self.ip + 2);
-
-		if verb != 0 {
-			self.ip = noun as usize;
-		} else {
-			self.ip += 3;
-		}
-	}
-
-	fn register_input(&mut self, input: i64) {
-		self.inputs.push_back(input);
-	}
-
-	fn has_output(&self) -> bool {
-		return !self.outputs.is_empty();
-	}
-
-	fn jump_if_false(&mut self, modes: Vec<OpModes>) {
-		let verb = self.get_value(&modes[0], self.ip + 1);
-		let noun = self.get_value(&modes[1], self.ip + 2);
-		if verb == 0 {
-			self.ip = noun as usize;
-		} else {
-			self.ip += 3;
-		}
-	}
-
-	fn less_than(&mut self, modes: Vec<OpModes>) {
-		let verb = self.get_value(&modes[0], self.ip + 1);
-		let noun = self.get_value(&modes[1], self.ip + 2);
-
-		if verb < noun {
-			self.store(1, self.ip + 3, &modes[2]);
-		} else {
-			self.store(0, self.ip + 3, &modes[2]);
-		}
-	}
-
-	fn equal(&mut self, modes: Vec<OpModes>) {
-		let verb = self.get_value(&modes[0], self.ip + 1);
-		let noun = self.get_value(&modes[1], self.ip + 2);
-		if verb == noun {
-			self.store(1, self.ip + 3, &modes[2]);
-		} else {
-			self.store(0, self.ip + 3, &modes[2]);
-		}
-	}
-
-	fn relative_add(&mut self, modes: Vec<OpModes>) {
-		let verb = self.get_value(&modes[0], self.ip + 1);
-		self.base += verb;
-	}
-
-	fn get_mode(&self, input: i64) -> OpModes {
-		if input % 10 == 1 {
-			return OpModes::Immediate;
-		} else if input % 10 == 2 {
-			return OpModes::Relative;
-		}
-
-		OpModes::Parametric
-	}
-
-	fn step(&mut self) -> bool {
-		self.exit_code = 0;
-
-		let opcode = self.program[self.ip];
-		let code = OpCodes::from(opcode % 100);
-		let modes = vec![
-			self.get_mode(opcode / 100),
-			self.get_mode(opcode / 1000),
-			self.get_mode(opcode / 10000),
-		];
-
-		match code {
-			OpCodes::Add => self.add(modes),
-			OpCodes::Mul => self.mul(modes),
-			OpCodes::Input => self.input(&modes[0]),
-			OpCodes::Output => self.output(&modes[0]),
-			OpCodes::JIT => self.jump_if_true(modes),
-			OpCodes::JIF => self.jump_if_false(modes),
-			OpCodes::LT => self.less_than(modes),
-			OpCodes::EQ => self.equal(modes),
-			OpCodes::RA => self.relative_add(modes),
-			OpCodes::Halt => return false,
-		}
-
-		if self.exit_code > 0 {
-			return false;
-		}
-
-		self.ip += code.param_count();
-		true
-	}
-
-	fn run_until_output(&mut self) -> Option<i64> {
-		while self.step() {
-			if self.has_output() {
-				return Some(self.outputs.pop_front().unwrap());
+/**
+ * Handle the save and load commands of the game, returns false for
+ * any other line so it goes to the droid.
+ */
+fn checkpoint(machine: &mut OpMachine, last: &mut String, input: &str) -> bool {
+	match input.trim().split_once(' ') {
+		Some(("save", path)) => match save(machine, last, path) {
+			Ok(()) => println!("saved to {}", path),
+			Err(error) => println!("could not save to {}: {}", path, error),
+		},
+		Some(("load", path)) => match load(machine, path) {
+			Ok(text) => {
+				println!("loaded {}", path);
+				print!("{}", text);
+				*last = text;
 			}
-		}
-
-		return None;
+			Err(error) => println!("could not load {}: {}", path, error),
+		},
+		_ => return false,
 	}
 
-	fn run(&mut self) {
-		let mut result = self.run_until_output();
-		while result.is_some() {
-			println!("{}", result.unwrap());
-			result = self.run_until_output();
-		}
-	}
+	true
 }
 
 /**
  * I ran this challenge by hand, the biggest reason was that it was more fun than running a
  * bruteforce on it.
+ *
+ * While playing, "save <file>" stores the game and "load <file>" goes back to it.
+ * Starting with --play plays a new game from the start, starting with a saved file
+ * as argument resumes that game, both instead of playing the solution.
  */
-fn silver(program: Vec<i64>) -> i64 {
-	let solution = vec![
+fn silver(program: Vec<i64>, start: Option<String>) -> i64 {
+	let mut solution = vec![
 		"north",        // move to corridor
 		"east",         // move to passages
 		"take cake",    // take cake
@@ -253,56 +86,62 @@ fn silver(program: Vec<i64>) -> i64 {
 		"east",         // finish
 	];
 
-	let mut machine = OpMachine {
-		program: program.clone(),
-		inputs: VecDeque::new(),
-		outputs: VecDeque::new(),
-		base: 0,
-		ip: 0,
-		exit_code: 0,
-	};
+	let mut droid = Ascii::new(OpMachine::new(program.clone()));
+	let mut last = String::new();
+
+	match start.as_deref() {
+		None => {}
+		Some("--play") => solution.clear(),
+		Some(path) => match load(&mut droid.machine, path) {
+			Ok(text) => {
+				print!("{}", text);
+				last = text;
+				solution.clear();
+			}
+			Err(error) => {
+				eprintln!("could not load {}: {}", path, error);
+				process::exit(1);
+			}
+		},
+	}
 
 	for line in solution {
-		for ch in line.chars() {
-			machine.register_input(ch as i64);
-		}
-
-		machine.register_input(10);
+		droid.send_line(line).unwrap();
 	}
 
 	loop {
-		let output = machine.run_until_output();
+		let transcript = droid.read_until_prompt();
+		print!("{}", transcript.text);
+
+		if transcript.state != RunState::NeedsInput {
+			break;
+		}
 
-		if machine.exit_code == 2 {
-			let mut input = String::new();
-			io::stdin().read_line(&mut input).unwrap();
+		/* after a load the droid waits without showing anything */
+		if !transcript.text.is_empty() {
+			last = transcript.text;
+		}
 
-			for i in input.chars() {
-				machine.register_input(i as i64);
-			}
+		let mut input = String::new();
+		if io::stdin().read_line(&mut input).unwrap() == 0 {
+			break;
+		}
 
+		if checkpoint(&mut droid.machine, &mut last, &input) {
 			continue;
-		} else if !output.is_some() {
-			break;
 		}
 
-		print!("{}", char::from_u32(output.unwrap() as u32).unwrap());
+		if let Err(error) = droid.send_line(input.trim()) {
+			println!("{}, try again", error);
+		}
 	}
 
 	0
 }
 
 fn main() {
-	let mut program: Vec<i64> = include_str!("input")
-		.trim()
-		.split(',')
-		.map(|num| num.parse::<i64>().unwrap())
-		.collect();
-
-	for _ in 0..5000 {
-		program.push(0);
-	}
+	let program = intcode::parse(include_str!("input"));
 
 	println!("Silver:");
-	silver(program.clone());
+	silver(program.clone(), env::args().nth(1));
 }
@@ -0,0 +1,91 @@
+use intcode::{OpMachine, RunState};
+use std::collections::{HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+/**
+ * Benchmarks of the interpreter on puzzle programs, run with:
+ * cargo bench -p intcode
+ */
+fn bench<F: FnMut() -> i64>(name: &str, iterations: u32, mut run: F) {
+	/* warm up, and make sure the answer is right */
+	let answer = run();
+
+	let start = Instant::now();
+	for _ in 0..iterations {
+		run();
+	}
+
+	let elapsed: Duration = start.elapsed() / iterations;
+	println!(
+		"{:<12} {:>10.3} ms/run  (answer {})",
+		name,
+		elapsed.as_secs_f64() * 1000.0,
+		answer
+	);
+}
+
+fn run(program: &[i64], inputs: &[i64]) -> i64 {
+	let mut machine = OpMachine::new(program.to_vec());
+	for input in inputs {
+		machine.register_input(*input);
+	}
+
+	let mut last = 0;
+	while let RunState::Output(output) = machine.run_until_output() {
+		last = output;
+	}
+
+	last
+}
+
+fn main() {
+	let boost = intcode::parse(include_str!("../../day09/src/input"));
+	bench("day09 boost", 20, || run(&boost, &[2]));
+	bench("day09 run_for", 20, || {
+		/* outputs are queued, so the machine only stops when it halts */
+		let mut machine = OpMachine::new(boost.clone());
+		machine.register_input(2);
+		machine.run_for(usize::MAX);
+		machine.outputs[0]
+	});
+
+	let beam = intcode::parse(include_str!("../../day19/src/input"));
+	bench("day19 beam", 20, || {
+		let mut count = 0;
+		for y in 0..50 {
+			for x in 0..50 {
+				count += run(&beam, &[x, y]);
+			}
+		}
+
+		count
+	});
+
+	let maze = intcode::parse(include_str!("../../day15/src/input"));
+	bench("day15 forks", 20, || {
+		/* a breadth first search that forks the droid on every move */
+		let mut seen = HashSet::new();
+		let mut queue = VecDeque::new();
+		seen.insert((0, 0));
+		queue.push_back((OpMachine::new(maze.clone()), (0, 0), 0));
+
+		while let Some((droid, (x, y), steps)) = queue.pop_front() {
+			for (direction, dx, dy) in [(1, 0, -1), (2, 0, 1), (3, -1, 0), (4, 1, 0)].iter() {
+				let position = (x + dx, y + dy);
+				if !seen.insert(position) {
+					continue;
+				}
+
+				let mut fork = droid.fork();
+				fork.register_input(*direction);
+				match fork.run_until_output() {
+					RunState::Output(0) => {}
+					RunState::Output(2) => return steps + 1,
+					_ => queue.push_back((fork, position, steps + 1)),
+				}
+			}
+		}
+
+		0
+	});
+}
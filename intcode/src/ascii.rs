@@ -0,0 +1,113 @@
+use crate::{OpMachine, RunState};
+use std::fmt;
+
+/**
+ * What the machine produced when reading a line.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Output {
+	/* a line of text, without the newline */
+	Line(String),
+	/* an output outside of the ascii range, like a puzzle answer */
+	Value(i64),
+	/* the machine stopped before a line was complete */
+	Stopped(RunState),
+}
+
+/**
+ * Everything the machine produced until it stopped.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Transcript {
+	pub text: String,
+	pub values: Vec<i64>,
+	pub state: RunState,
+}
+
+/**
+ * Text that can not be sent, because of the character that is not ascii.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NotAscii(pub char);
+
+impl fmt::Display for NotAscii {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "'{}' is not an ascii character", self.0)
+	}
+}
+
+impl std::error::Error for NotAscii {}
+
+/**
+ * A machine that speaks ascii, it takes lines of text as input
+ * and outputs text, with values outside of ascii kept apart.
+ */
+#[derive(Clone)]
+pub struct Ascii {
+	pub machine: OpMachine,
+	/* the text of the line that is being read */
+	line: String,
+}
+
+fn is_ascii(value: i64) -> bool {
+	(0..128).contains(&value)
+}
+
+impl Ascii {
+	pub fn new(machine: OpMachine) -> Ascii {
+		Ascii {
+			machine,
+			line: String::new(),
+		}
+	}
+
+	/// Send the line followed by a newline, nothing is sent when
+	/// it holds a character that is not ascii.
+	pub fn send_line(&mut self, line: &str) -> Result<(), NotAscii> {
+		if let Some(ch) = line.chars().find(|ch| !ch.is_ascii()) {
+			return Err(NotAscii(ch));
+		}
+
+		for ch in line.chars() {
+			self.machine.register_input(ch as i64);
+		}
+
+		self.machine.register_input(10);
+		Ok(())
+	}
+
+	/// Run until a line is complete, a value outside of ascii is
+	/// output, or the machine stops. A line that is cut off by the
+	/// machine stopping is returned before the stop.
+	pub fn read_line(&mut self) -> Output {
+		loop {
+			match self.machine.run_until_output() {
+				RunState::Output(10) => return Output::Line(self.line.split_off(0)),
+				RunState::Output(value) if is_ascii(value) => self.line.push(value as u8 as char),
+				RunState::Output(value) => return Output::Value(value),
+				_ if !self.line.is_empty() => return Output::Line(self.line.split_off(0)),
+				state => return Output::Stopped(state),
+			}
+		}
+	}
+
+	/// Run until the machine asks for input, halts or faults.
+	pub fn read_until_prompt(&mut self) -> Transcript {
+		let mut text = self.line.split_off(0);
+		let mut values = Vec::new();
+
+		loop {
+			match self.machine.run_until_output() {
+				RunState::Output(value) if is_ascii(value) => text.push(value as u8 as char),
+				RunState::Output(value) => values.push(value),
+				state => {
+					return Transcript {
+						text,
+						values,
+						state,
+					}
+				}
+			}
+		}
+	}
+}
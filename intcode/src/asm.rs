@@ -0,0 +1,305 @@
+use crate::{OpCodes, OpModes};
+use std::collections::HashMap;
+use std::fmt;
+
+/**
+ * An assembly error, with the (1 based) line it was found on.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Error {
+	pub line: usize,
+	pub message: String,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "line {}: {}", self.line, self.message)
+	}
+}
+
+impl std::error::Error for Error {}
+
+/**
+ * A number, or a label plus an offset which is resolved
+ * once all labels are known.
+ */
+#[derive(Clone, Debug)]
+enum Value {
+	Number(i64),
+	Label(String, i64),
+}
+
+#[derive(Clone, Debug)]
+enum Item {
+	Instruction(OpCodes, Vec<(OpModes, Value)>),
+	Data(Vec<Value>),
+}
+
+struct Statement {
+	line: usize,
+	item: Item,
+}
+
+fn is_identifier(name: &str) -> bool {
+	let mut chars = name.chars();
+	match chars.next() {
+		Some(ch) if ch.is_ascii_alphabetic() || ch == '_' => {
+			chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+		}
+		_ => false,
+	}
+}
+
+/// Split on the separator, but not inside a string literal.
+fn split_outside_quotes(text: &str, separator: char) -> Vec<&str> {
+	let mut parts = Vec::new();
+	let mut quoted = false;
+	let mut start = 0;
+
+	for (i, ch) in text.char_indices() {
+		if ch == '"' {
+			quoted = !quoted;
+		} else if ch == separator && !quoted {
+			parts.push(&text[start..i]);
+			start = i + 1;
+		}
+	}
+
+	parts.push(&text[start..]);
+	parts
+}
+
+fn parse_value(text: &str) -> Result<Value, String> {
+	let text = text.trim();
+	if let Ok(number) = text.parse::<i64>() {
+		return Ok(Value::Number(number));
+	}
+
+	/* label, label+offset or label-offset */
+	let (name, offset) = match text.find(['+', '-']) {
+		Some(i) => {
+			let offset = text[i..].replace(' ', "").parse::<i64>();
+			match offset {
+				Ok(offset) => (text[..i].trim(), offset),
+				Err(_) => return Err(format!("invalid offset in '{}'", text)),
+			}
+		}
+		None => (text, 0),
+	};
+
+	if !is_identifier(name) {
+		return Err(format!("invalid value '{}'", text));
+	}
+
+	Ok(Value::Label(name.to_string(), offset))
+}
+
+fn parse_operand(text: &str) -> Result<(OpModes, Value), String> {
+	let text = text.trim();
+
+	if text.starts_with('[') && text.ends_with(']') {
+		return Ok((OpModes::Parametric, parse_value(&text[1..text.len() - 1])?));
+	}
+
+	if let Some(value) = text.strip_prefix('#') {
+		return Ok((OpModes::Immediate, parse_value(value)?));
+	}
+
+	if let Some(offset) = text.strip_prefix("rb") {
+		let offset = offset.trim();
+		let value = match offset.strip_prefix('+') {
+			Some(offset) => parse_value(offset)?,
+			None if offset.starts_with('-') => match parse_value(&offset[1..])? {
+				Value::Number(number) => Value::Number(-number),
+				Value::Label(..) => return Err(format!("can not negate label in '{}'", text)),
+			},
+			None => return Err(format!("invalid relative operand '{}'", text)),
+		};
+
+		return Ok((OpModes::Relative, value));
+	}
+
+	Err(format!(
+		"invalid operand '{}', expected [addr], #imm or rb+off",
+		text
+	))
+}
+
+fn parse_data(text: &str) -> Result<Vec<Value>, String> {
+	let mut values = Vec::new();
+
+	for part in split_outside_quotes(text, ',') {
+		let part = part.trim();
+		if part.len() >= 2 && part.starts_with('"') && part.ends_with('"') {
+			let string = &part[1..part.len() - 1];
+			if !string.is_ascii() {
+				return Err(format!("string {} is not ascii", part));
+			}
+
+			values.extend(string.chars().map(|ch| Value::Number(ch as i64)));
+		} else {
+			values.push(parse_value(part)?);
+		}
+	}
+
+	Ok(values)
+}
+
+/// Write parameters can not be immediate.
+fn is_write(code: OpCodes, index: usize) -> bool {
+	match code {
+		OpCodes::Add | OpCodes::Mul | OpCodes::LT | OpCodes::EQ => index == 2,
+		OpCodes::Input => index == 0,
+		_ => false,
+	}
+}
+
+fn parse_item(mnemonic: &str, rest: &str) -> Result<Item, String> {
+	if mnemonic.eq_ignore_ascii_case("DB") {
+		return Ok(Item::Data(parse_data(rest)?));
+	}
+
+	let code = match OpCodes::from_mnemonic(mnemonic) {
+		Some(code) => code,
+		None => return Err(format!("unknown mnemonic '{}'", mnemonic)),
+	};
+
+	let parts: Vec<&str> = match rest.trim() {
+		"" => vec![],
+		rest => rest.split(',').collect(),
+	};
+
+	if parts.len() != code.arity() {
+		return Err(format!(
+			"{} takes {} operands, found {}",
+			code.mnemonic(),
+			code.arity(),
+			parts.len()
+		));
+	}
+
+	let mut operands = Vec::new();
+	for (i, part) in parts.iter().enumerate() {
+		let operand = parse_operand(part)?;
+		if operand.0 == OpModes::Immediate && is_write(code, i) {
+			return Err(format!(
+				"operand {} of {} is written to and can not be immediate",
+				i + 1,
+				code.mnemonic()
+			));
+		}
+
+		operands.push(operand);
+	}
+
+	Ok(Item::Instruction(code, operands))
+}
+
+fn size(item: &Item) -> usize {
+	match item {
+		Item::Instruction(_, operands) => operands.len() + 1,
+		Item::Data(values) => values.len(),
+	}
+}
+
+fn resolve(value: &Value, labels: &HashMap<String, usize>) -> Result<i64, String> {
+	match value {
+		Value::Number(number) => Ok(*number),
+		Value::Label(name, offset) => match labels.get(name) {
+			Some(address) => Ok(*address as i64 + offset),
+			None => Err(format!("undefined label '{}'", name)),
+		},
+	}
+}
+
+/**
+ * Assemble the source text into a program.
+ *
+ * Every line holds optional labels, followed by an instruction or data:
+ *   loop: ADD [counter], #1, [counter] ; comments start with a semicolon
+ *         JIT #1, #loop
+ *   counter: DB 0, "text", loop+2
+ * Operands are written [addr] for position, #imm for immediate and
+ * rb+off for relative mode, the mnemonics are the disassembler's.
+ * A numeric label, as in a listing, checks the current address.
+ */
+pub fn assemble(source: &str) -> Result<Vec<i64>, Error> {
+	let mut labels: HashMap<String, usize> = HashMap::new();
+	let mut statements: Vec<Statement> = Vec::new();
+	let mut address = 0;
+
+	for (i, line) in source.lines().enumerate() {
+		let error = |message: String| Error {
+			line: i + 1,
+			message,
+		};
+
+		let mut text = split_outside_quotes(line, ';')[0].trim();
+
+		while let Some(colon) = text.find(':') {
+			let label = text[..colon].trim();
+
+			if let Ok(expected) = label.parse::<usize>() {
+				if expected != address {
+					return Err(error(format!(
+						"address {} expected, but it is {}",
+						expected, address
+					)));
+				}
+			} else if is_identifier(label) {
+				if labels.insert(label.to_string(), address).is_some() {
+					return Err(error(format!("duplicate label '{}'", label)));
+				}
+			} else {
+				break;
+			}
+
+			text = text[colon + 1..].trim();
+		}
+
+		if text.is_empty() {
+			continue;
+		}
+
+		let (mnemonic, rest) = match text.find(char::is_whitespace) {
+			Some(space) => (&text[..space], &text[space..]),
+			None => (text, ""),
+		};
+
+		let item = parse_item(mnemonic, rest).map_err(error)?;
+		address += size(&item);
+		statements.push(Statement { line: i + 1, item });
+	}
+
+	let mut program: Vec<i64> = Vec::new();
+	for statement in statements {
+		let error = |message: String| Error {
+			line: statement.line,
+			message,
+		};
+
+		match &statement.item {
+			Item::Instruction(code, operands) => {
+				let mut instruction = *code as i64;
+				let mut factor = 100;
+
+				for (mode, _) in operands {
+					instruction += factor * *mode as i64;
+					factor *= 10;
+				}
+
+				program.push(instruction);
+				for (_, value) in operands {
+					program.push(resolve(value, &labels).map_err(error)?);
+				}
+			}
+			Item::Data(values) => {
+				for value in values {
+					program.push(resolve(value, &labels).map_err(error)?);
+				}
+			}
+		}
+	}
+
+	Ok(program)
+}
@@ -0,0 +1,86 @@
+use crate::{OpMachine, RunState};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/**
+ * The outputs of a machine that ran to a stop, and the state it stopped in.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Run {
+	pub outputs: Vec<i64>,
+	pub state: RunState,
+}
+
+/// The number of threads to use, one per available core.
+pub fn threads() -> usize {
+	thread::available_parallelism()
+		.map(|n| n.get())
+		.unwrap_or(1)
+}
+
+/**
+ * Apply job to every item on a pool of threads, the results are in
+ * the order of the items no matter which thread finished first.
+ */
+pub fn map_on<T, R, F>(threads: usize, items: &[T], job: F) -> Vec<R>
+where
+	T: Sync,
+	R: Send,
+	F: Fn(&T) -> R + Sync,
+{
+	let next = AtomicUsize::new(0);
+
+	let mut results: Vec<(usize, R)> = thread::scope(|scope| {
+		let workers: Vec<_> = (0..threads.max(1))
+			.map(|_| {
+				scope.spawn(|| {
+					let mut done = Vec::new();
+					loop {
+						let i = next.fetch_add(1, Ordering::Relaxed);
+						if i >= items.len() {
+							return done;
+						}
+
+						done.push((i, job(&items[i])));
+					}
+				})
+			})
+			.collect();
+
+		workers
+			.into_iter()
+			.flat_map(|worker| worker.join().unwrap())
+			.collect()
+	});
+
+	results.sort_by_key(|(i, _)| *i);
+	results.into_iter().map(|(_, result)| result).collect()
+}
+
+pub fn map<T, R, F>(items: &[T], job: F) -> Vec<R>
+where
+	T: Sync,
+	R: Send,
+	F: Fn(&T) -> R + Sync,
+{
+	map_on(threads(), items, job)
+}
+
+/**
+ * Run the machine once for every list of inputs, each run on a fork
+ * of it, until it halts, faults or needs more input.
+ */
+pub fn run(machine: &OpMachine, inputs: &[Vec<i64>]) -> Vec<Run> {
+	map(inputs, |inputs| {
+		let mut machine = machine.fork();
+		machine.inputs.extend(inputs);
+
+		let mut outputs = Vec::new();
+		loop {
+			match machine.run_until_output() {
+				RunState::Output(output) => outputs.push(output),
+				state => return Run { outputs, state },
+			}
+		}
+	})
+}
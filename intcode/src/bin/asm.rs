@@ -0,0 +1,29 @@
+use intcode::asm;
+use std::env;
+use std::fs;
+
+/**
+ * Assemble a source file into a comma separated intcode program, for example:
+ * cargo run -p intcode --bin asm -- program.asm
+ */
+fn main() {
+	let path = match env::args().nth(1) {
+		Some(path) => path,
+		None => {
+			eprintln!("usage: asm <source>");
+			std::process::exit(1);
+		}
+	};
+
+	let source = fs::read_to_string(&path).expect("could not read source");
+	match asm::assemble(&source) {
+		Ok(program) => {
+			let words: Vec<String> = program.iter().map(|word| word.to_string()).collect();
+			println!("{}", words.join(","));
+		}
+		Err(error) => {
+			eprintln!("{}: {}", path, error);
+			std::process::exit(1);
+		}
+	}
+}
@@ -0,0 +1,39 @@
+use intcode::debugger::Debugger;
+use intcode::OpMachine;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+/**
+ * Interactive debugger for an intcode program, for example:
+ * cargo run -p intcode --bin debug -- day25/src/input
+ */
+fn main() {
+	let path = match env::args().nth(1) {
+		Some(path) => path,
+		None => {
+			eprintln!("usage: debug <program>");
+			std::process::exit(1);
+		}
+	};
+
+	let program = intcode::parse(&fs::read_to_string(path).expect("could not read program"));
+	let mut debugger = Debugger::new(OpMachine::new(program));
+	print!("{}", debugger.disassemble(0, 1));
+
+	let stdin = io::stdin();
+	loop {
+		print!("(icdb) ");
+		io::stdout().flush().unwrap();
+
+		let mut line = String::new();
+		if stdin.lock().read_line(&mut line).unwrap() == 0 {
+			break;
+		}
+
+		match line.trim() {
+			"quit" | "q" => break,
+			command => print!("{}", debugger.execute(command)),
+		}
+	}
+}
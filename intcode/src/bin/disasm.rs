@@ -0,0 +1,20 @@
+use intcode::disasm;
+use std::env;
+use std::fs;
+
+/**
+ * Print the listing of an intcode program, for example:
+ * cargo run -p intcode --bin disasm -- day17/src/input
+ */
+fn main() {
+	let path = match env::args().nth(1) {
+		Some(path) => path,
+		None => {
+			eprintln!("usage: disasm <program>");
+			std::process::exit(1);
+		}
+	};
+
+	let program = intcode::parse(&fs::read_to_string(path).expect("could not read program"));
+	print!("{}", disasm::listing(&program));
+}
@@ -0,0 +1,69 @@
+use intcode::guard::{Guard, Policy};
+use intcode::{OpMachine, RunState};
+use std::env;
+use std::fs;
+use std::io;
+
+/**
+ * Run an intcode program with the given inputs and print where the
+ * steps went, with --trace every instruction goes to stderr and with
+ * --guard the writes to executed code are listed, e.g.:
+ * cargo run -p intcode --release --bin profile -- day09/src/input 2
+ */
+fn main() {
+	let mut args: Vec<String> = env::args().skip(1).collect();
+	let trace = args.iter().any(|arg| arg == "--trace");
+	let guard = args.iter().any(|arg| arg == "--guard");
+	args.retain(|arg| arg != "--trace" && arg != "--guard");
+
+	if args.is_empty() {
+		eprintln!("usage: profile [--trace] [--guard] <program> [inputs...]");
+		std::process::exit(1);
+	}
+
+	let program = intcode::parse(&fs::read_to_string(&args[0]).expect("could not read program"));
+	let mut machine = OpMachine::new(program);
+	for input in &args[1..] {
+		machine.register_input(input.parse::<i64>().expect("inputs should be numbers"));
+	}
+
+	machine.enable_profile();
+	if trace {
+		machine.trace_to(io::stderr());
+	}
+
+	if guard {
+		machine.guard = Some(Guard::new(Policy::Warn));
+	}
+
+	loop {
+		match machine.run_until_output() {
+			RunState::Output(output) => println!("output: {}", output),
+			RunState::Halted => break,
+			RunState::NeedsInput => {
+				println!("stopped: needs input");
+				break;
+			}
+			RunState::Fault(error) => {
+				println!("stopped: {}", error);
+				break;
+			}
+		}
+	}
+
+	print!("{}", machine.profile.unwrap());
+
+	if let Some(guard) = machine.guard {
+		println!("writes to code: {}", guard.warnings.len());
+		for write in guard.warnings {
+			let by = match write.ip {
+				Some(ip) => format!("ip {:>6}", ip),
+				None => "outside".to_string(),
+			};
+			println!(
+				"  {} wrote {} over {} at {}",
+				by, write.new, write.old, write.address
+			);
+		}
+	}
+}
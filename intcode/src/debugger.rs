@@ -0,0 +1,339 @@
+use crate::disasm::{self, Instruction, Line};
+use crate::{OpCodes, OpMachine, RunState};
+use std::collections::{BTreeMap, BTreeSet};
+
+/* the most instructions a listing or cells a dump shows, more would flood the terminal */
+const MAX_SHOWN: i64 = 4096;
+
+/**
+ * Why the debugger handed control back.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Stop {
+	/* the requested number of steps was executed */
+	Stepped,
+	Breakpoint(usize),
+	Opcode(OpCodes),
+	Watch { address: usize, old: i64, new: i64 },
+	State(RunState),
+}
+
+/**
+ * A step debugger around OpMachine, with breakpoints on addresses
+ * and opcodes, and watches on memory cells.
+ */
+pub struct Debugger {
+	pub machine: OpMachine,
+	pub breakpoints: BTreeSet<usize>,
+	pub opcodes: Vec<OpCodes>,
+	/* watched address and the value it was last seen with */
+	pub watches: BTreeMap<usize, i64>,
+	last: String,
+}
+
+impl Debugger {
+	pub fn new(machine: OpMachine) -> Debugger {
+		Debugger {
+			machine,
+			breakpoints: BTreeSet::new(),
+			opcodes: Vec::new(),
+			watches: BTreeMap::new(),
+			last: String::new(),
+		}
+	}
+
+	fn opcode_at_ip(&self) -> Option<OpCodes> {
+		OpCodes::from(self.machine.program.get(self.machine.ip) % 100)
+	}
+
+	/// Execute one instruction and report if it triggered a watch,
+	/// or stopped the machine.
+	fn single_step(&mut self) -> Option<Stop> {
+		match self.machine.step() {
+			Ok(Some(state)) => return Some(Stop::State(state)),
+			Err(error) => return Some(Stop::State(RunState::Fault(error))),
+			Ok(None) => {}
+		}
+
+		for (address, old) in self.watches.iter_mut() {
+			let new = self.machine.program.get(*address);
+			if new != *old {
+				let stop = Stop::Watch {
+					address: *address,
+					old: *old,
+					new,
+				};
+				*old = new;
+				return Some(stop);
+			}
+		}
+
+		None
+	}
+
+	/// Execute up to count instructions, the breakpoint at the
+	/// current instruction pointer is not hit again.
+	pub fn step(&mut self, count: usize) -> Stop {
+		for i in 0..count {
+			if i > 0 {
+				if let Some(stop) = self.check_breakpoints() {
+					return stop;
+				}
+			}
+
+			if let Some(stop) = self.single_step() {
+				return stop;
+			}
+		}
+
+		Stop::Stepped
+	}
+
+	/// Run until a breakpoint or watch triggers, or the machine stops.
+	pub fn cont(&mut self) -> Stop {
+		if let Some(stop) = self.single_step() {
+			return stop;
+		}
+
+		loop {
+			if let Some(stop) = self.check_breakpoints() {
+				return stop;
+			}
+
+			if let Some(stop) = self.single_step() {
+				return stop;
+			}
+		}
+	}
+
+	fn check_breakpoints(&self) -> Option<Stop> {
+		if self.breakpoints.contains(&self.machine.ip) {
+			return Some(Stop::Breakpoint(self.machine.ip));
+		}
+
+		match self.opcode_at_ip() {
+			Some(code) if self.opcodes.contains(&code) => Some(Stop::Opcode(code)),
+			_ => None,
+		}
+	}
+
+	pub fn watch(&mut self, address: usize) {
+		let value = self.machine.program.get(address);
+		self.watches.insert(address, value);
+	}
+
+	pub fn registers(&self) -> String {
+		format!("ip {}  base {}", self.machine.ip, self.machine.base)
+	}
+
+	/// Print memory from..to (exclusive), eight cells on a line.
+	pub fn dump(&self, from: usize, to: usize) -> String {
+		let mut output = String::new();
+
+		for row in (from..to).step_by(8) {
+			output += &format!("{:>6}:", row);
+			for address in row..(row + 8).min(to) {
+				output += &format!(" {:>6}", self.machine.program.get(address));
+			}
+			output += "\n";
+		}
+
+		output
+	}
+
+	/// Disassemble around the instruction pointer, the lines before it
+	/// come from the listing, from the instruction pointer on the memory
+	/// is decoded as instructions.
+	pub fn disassemble(&self, before: usize, after: usize) -> String {
+		let memory = self.machine.program.to_vec();
+		let ip = self.machine.ip;
+		let mut output = String::new();
+
+		let lines: Vec<Line> = disasm::disassemble(&memory[..ip.min(memory.len())]);
+		for line in lines.iter().skip(lines.len().saturating_sub(before)) {
+			output += &format!("   {}\n", line);
+		}
+
+		let mut address = ip;
+		for i in 0..after {
+			let marker = if i == 0 { "=> " } else { "   " };
+			let line = match Instruction::decode(&memory, address) {
+				Some(instruction) => Line::Code {
+					address,
+					instruction,
+				},
+				None => Line::Data {
+					address,
+					value: self.machine.program.get(address),
+				},
+			};
+
+			address += match &line {
+				Line::Code { instruction, .. } => instruction.size(),
+				Line::Data { .. } => 1,
+			};
+			output += &format!("{}{}\n", marker, line);
+		}
+
+		output
+	}
+
+	/// The outputs the machine produced so far, they stay queued.
+	pub fn outputs(&self) -> Vec<i64> {
+		self.machine.outputs.iter().copied().collect()
+	}
+
+	/// Execute a debugger command and return the text to show,
+	/// an empty command repeats the last one.
+	pub fn execute(&mut self, command: &str) -> String {
+		let command = match command.trim() {
+			"" => self.last.clone(),
+			command => command.to_string(),
+		};
+		self.last = command.clone();
+
+		let mut words = command.split_whitespace();
+		let name = words.next().unwrap_or("");
+		let args: Vec<&str> = words.collect();
+		let numbers: Result<Vec<i64>, _> = args.iter().map(|arg| arg.parse::<i64>()).collect();
+		let numbers = match numbers {
+			Ok(numbers) => numbers,
+			Err(_) if !matches!(name, "break" | "b" | "input" | "i" | "delete" | "d") => {
+				return format!("invalid number in '{}'\n", command);
+			}
+			Err(_) => vec![],
+		};
+
+		match (name, numbers.as_slice()) {
+			("step" | "s", []) => self.report(|debugger| debugger.step(1)),
+			("step" | "s", [count]) if *count > 0 => {
+				let count = *count as usize;
+				self.report(|debugger| debugger.step(count))
+			}
+			("step" | "s", [_]) => "the step count should be positive\n".to_string(),
+			("continue" | "c", []) => self.report(Debugger::cont),
+			("break" | "b", _) => self.add_breakpoint(&args),
+			("delete" | "d", _) => self.delete_breakpoint(&args),
+			("watch" | "w", [address]) if *address >= 0 => {
+				self.watch(*address as usize);
+				format!("watching {}\n", address)
+			}
+			("registers" | "r", []) => format!("{}\n", self.registers()),
+			("dump" | "x", [from]) if *from >= 0 => self.dump(*from as usize, *from as usize + 8),
+			("dump" | "x", [from, to]) if *from >= 0 && to > from && to - from <= MAX_SHOWN => {
+				self.dump(*from as usize, *to as usize)
+			}
+			("dump" | "x", [_, _]) => {
+				format!(
+					"the range should be increasing and at most {} cells\n",
+					MAX_SHOWN
+				)
+			}
+			("disassemble" | "l", []) => self.disassemble(3, 6),
+			("disassemble" | "l", [count]) if (0..=MAX_SHOWN / 2).contains(count) => {
+				self.disassemble(*count as usize, *count as usize * 2)
+			}
+			("disassemble" | "l", [_]) => {
+				format!("the count should be between 0 and {}\n", MAX_SHOWN / 2)
+			}
+			("input" | "i", _) => self.input(&command, &args),
+			("output" | "o", []) => {
+				let outputs: Vec<String> = self.outputs().iter().map(|x| x.to_string()).collect();
+				format!("[{}]\n", outputs.join(", "))
+			}
+			("help" | "h", _) => HELP.to_string(),
+			_ => format!("unknown command '{}', try help\n", command),
+		}
+	}
+
+	fn report<F: FnOnce(&mut Debugger) -> Stop>(&mut self, run: F) -> String {
+		let stop = run(self);
+		let reason = match stop {
+			Stop::Stepped => String::new(),
+			Stop::Breakpoint(address) => format!("breakpoint at {}\n", address),
+			Stop::Opcode(code) => format!("breakpoint on {}\n", code.mnemonic()),
+			Stop::Watch { address, old, new } => {
+				format!("watch {} changed {} -> {}\n", address, old, new)
+			}
+			Stop::State(RunState::Fault(error)) => format!("fault: {}\n", error),
+			Stop::State(state) => format!("{:?}\n", state),
+		};
+
+		format!("{}{}", reason, self.disassemble(0, 1))
+	}
+
+	fn add_breakpoint(&mut self, args: &[&str]) -> String {
+		match args {
+			["op", mnemonic] => match OpCodes::from_mnemonic(mnemonic) {
+				Some(code) => {
+					self.opcodes.push(code);
+					format!("breakpoint on {}\n", code.mnemonic())
+				}
+				None => format!("unknown mnemonic '{}'\n", mnemonic),
+			},
+			[address] => match address.parse::<usize>() {
+				Ok(address) => {
+					self.breakpoints.insert(address);
+					format!("breakpoint at {}\n", address)
+				}
+				Err(_) => format!("invalid address '{}'\n", address),
+			},
+			_ => "usage: break <address> | break op <mnemonic>\n".to_string(),
+		}
+	}
+
+	fn delete_breakpoint(&mut self, args: &[&str]) -> String {
+		match args {
+			["op", mnemonic] => {
+				self.opcodes
+					.retain(|code| !code.mnemonic().eq_ignore_ascii_case(mnemonic));
+				format!("deleted breakpoint on {}\n", mnemonic)
+			}
+			[address] => match address.parse::<usize>() {
+				Ok(address) => {
+					self.breakpoints.remove(&address);
+					self.watches.remove(&address);
+					format!("deleted {}\n", address)
+				}
+				Err(_) => format!("invalid address '{}'\n", address),
+			},
+			_ => "usage: delete <address> | delete op <mnemonic>\n".to_string(),
+		}
+	}
+
+	/// Register numbers, or with a quoted string its characters and a newline.
+	fn input(&mut self, command: &str, args: &[&str]) -> String {
+		if let (Some(start), Some(end)) = (command.find('"'), command.rfind('"')) {
+			if start < end {
+				for ch in command[start + 1..end].chars() {
+					self.machine.register_input(ch as i64);
+				}
+				self.machine.register_input(10);
+				return format!("{} inputs queued\n", self.machine.inputs.len());
+			}
+		}
+
+		for arg in args {
+			match arg.parse::<i64>() {
+				Ok(value) => self.machine.register_input(value),
+				Err(_) => return format!("invalid input '{}'\n", arg),
+			}
+		}
+
+		format!("{} inputs queued\n", self.machine.inputs.len())
+	}
+}
+
+const HELP: &str = "\
+step [n]            execute n instructions (s)
+continue            run until a breakpoint, watch or stop (c)
+break <addr>        break at an address (b)
+break op <mnemonic> break on an opcode, e.g. break op IN
+delete <addr>       remove a breakpoint or watch (d)
+watch <addr>        stop when a memory cell changes (w)
+registers           print ip and base (r)
+dump <from> [to]    print memory (x)
+disassemble [n]     disassemble around ip (l)
+input <values>      queue inputs, input \"text\" queues ascii (i)
+output              show the queued outputs (o)
+";
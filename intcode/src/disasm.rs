@@ -0,0 +1,251 @@
+use crate::{decode, OpCodes, OpModes};
+use std::collections::HashSet;
+use std::fmt;
+
+/**
+ * A decoded parameter, printed as [addr], #imm or rb+off.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operand {
+	Position(i64),
+	Immediate(i64),
+	Relative(i64),
+}
+
+impl Operand {
+	pub(crate) fn from(mode: OpModes, value: i64) -> Operand {
+		match mode {
+			OpModes::Parametric => Operand::Position(value),
+			OpModes::Immediate => Operand::Immediate(value),
+			OpModes::Relative => Operand::Relative(value),
+		}
+	}
+}
+
+impl fmt::Display for Operand {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Operand::Position(address) => write!(f, "[{}]", address),
+			Operand::Immediate(value) => write!(f, "#{}", value),
+			Operand::Relative(offset) if *offset < 0 => write!(f, "rb-{}", -offset),
+			Operand::Relative(offset) => write!(f, "rb+{}", offset),
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Instruction {
+	pub code: OpCodes,
+	pub operands: Vec<Operand>,
+}
+
+impl Instruction {
+	/// Decode the instruction at address, None when the word there is
+	/// not a valid instruction or its parameters run off the program.
+	pub fn decode(program: &[i64], address: usize) -> Option<Instruction> {
+		let (code, modes) = decode(*program.get(address)?).ok()?;
+		let mut operands = Vec::new();
+
+		for (i, mode) in modes.iter().take(code.arity()).enumerate() {
+			operands.push(Operand::from(*mode, *program.get(address + i + 1)?));
+		}
+
+		Some(Instruction { code, operands })
+	}
+
+	/// The number of words the instruction occupies.
+	pub fn size(&self) -> usize {
+		self.operands.len() + 1
+	}
+
+	/// The constant an ADD or MUL of two immediates writes to the stack,
+	/// None when it overflows.
+	fn pushed_constant(&self) -> Option<usize> {
+		let constant = match (self.code, self.operands.as_slice()) {
+			(
+				OpCodes::Add,
+				[Operand::Immediate(a), Operand::Immediate(b), Operand::Relative(_)],
+			) => a.checked_add(*b)?,
+			(
+				OpCodes::Mul,
+				[Operand::Immediate(a), Operand::Immediate(b), Operand::Relative(_)],
+			) => a.checked_mul(*b)?,
+			_ => return None,
+		};
+
+		if constant < 0 {
+			return None;
+		}
+
+		Some(constant as usize)
+	}
+
+	/// The addresses execution can continue at after this instruction,
+	/// jumps through memory can not be followed and are left out.
+	fn successors(&self, address: usize) -> Vec<usize> {
+		let next = address + self.size();
+
+		match self.code {
+			OpCodes::Halt => vec![],
+			OpCodes::JIT | OpCodes::JIF => {
+				/* a constant condition either always or never jumps */
+				let jumps = match self.operands[0] {
+					Operand::Immediate(value) => Some((value != 0) == (self.code == OpCodes::JIT)),
+					_ => None,
+				};
+
+				let mut successors = Vec::new();
+				if jumps != Some(true) {
+					successors.push(next);
+				}
+
+				if let (Some(true) | None, Operand::Immediate(target)) = (jumps, self.operands[1]) {
+					if target >= 0 {
+						successors.push(target as usize);
+					}
+				}
+
+				successors
+			}
+			_ => vec![next],
+		}
+	}
+}
+
+impl fmt::Display for Instruction {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", self.code.mnemonic())?;
+
+		for (i, operand) in self.operands.iter().enumerate() {
+			let separator = if i == 0 { " " } else { ", " };
+			write!(f, "{}{}", separator, operand)?;
+		}
+
+		Ok(())
+	}
+}
+
+/**
+ * A line of the listing, either an instruction or a data word.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Line {
+	Code {
+		address: usize,
+		instruction: Instruction,
+	},
+	Data {
+		address: usize,
+		value: i64,
+	},
+}
+
+impl fmt::Display for Line {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Line::Code {
+				address,
+				instruction,
+			} => write!(f, "{:>6}: {}", address, instruction),
+			Line::Data { address, value } => write!(f, "{:>6}: DB {}", address, value),
+		}
+	}
+}
+
+/**
+ * Find the addresses of all instructions reachable from address 0.
+ * Jumps with an immediate target are followed, everything that
+ * can not be reached this way is considered to be data.
+ *
+ * Returning from a call jumps through the stack, so that can not be
+ * followed. Instead a constant pushed on the stack (written relative
+ * to the base) that points right after an unconditional jump is taken
+ * as a return address and followed as well.
+ */
+pub fn reachable(program: &[i64]) -> HashSet<usize> {
+	let mut seen: HashSet<usize> = HashSet::new();
+	let mut pushed: HashSet<usize> = HashSet::new();
+	let mut calls: Vec<usize> = Vec::new();
+	let mut todo: Vec<usize> = vec![0];
+
+	loop {
+		while let Some(address) = todo.pop() {
+			if seen.contains(&address) {
+				continue;
+			}
+
+			let instruction = match Instruction::decode(program, address) {
+				Some(instruction) => instruction,
+				None => continue,
+			};
+
+			if let Some(constant) = instruction.pushed_constant() {
+				pushed.insert(constant);
+			}
+
+			let successors = instruction.successors(address);
+			if !successors.contains(&(address + instruction.size())) {
+				calls.push(address + instruction.size());
+			}
+
+			seen.insert(address);
+			todo.extend(successors);
+		}
+
+		let (returns, rest): (Vec<usize>, Vec<usize>) =
+			calls.iter().partition(|next| pushed.contains(next));
+		calls = rest;
+		todo = returns;
+
+		if todo.is_empty() {
+			return seen;
+		}
+	}
+}
+
+/**
+ * Walk the image and split it into instructions and data.
+ */
+pub fn disassemble(program: &[i64]) -> Vec<Line> {
+	let code = reachable(program);
+	let mut lines = Vec::new();
+	let mut address = 0;
+
+	while address < program.len() {
+		let instruction = if code.contains(&address) {
+			Instruction::decode(program, address)
+		} else {
+			None
+		};
+
+		match instruction {
+			Some(instruction) => {
+				let size = instruction.size();
+				lines.push(Line::Code {
+					address,
+					instruction,
+				});
+				address += size;
+			}
+			None => {
+				lines.push(Line::Data {
+					address,
+					value: program[address],
+				});
+				address += 1;
+			}
+		}
+	}
+
+	lines
+}
+
+/**
+ * The listing of the program, one line per instruction or data word.
+ */
+pub fn listing(program: &[i64]) -> String {
+	disassemble(program)
+		.iter()
+		.map(|line| format!("{}\n", line))
+		.collect()
+}
@@ -0,0 +1,70 @@
+use std::fmt;
+
+/**
+ * The reason an instruction could not be executed.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+	/* the opcode (instruction % 100) is not known */
+	InvalidOpcode(i64),
+	/* the mode digit of a parameter is not 0, 1 or 2 */
+	InvalidMode(i64),
+	/* a write parameter uses the immediate mode */
+	ImmediateWrite,
+	/* an address or jump target is below zero */
+	NegativeAddress(i64),
+	/* the machine executed as many instructions as it may */
+	StepLimit(u64),
+	/* an address at or past the memory limit was used */
+	MemoryLimit(usize),
+	/* the deadline passed */
+	Deadline,
+	/* a write to an address that was executed as code */
+	CodeWrite(usize),
+	/* a write to an address in a read-only range */
+	ReadOnly(usize),
+	/* an add, multiply or relative base adjustment overflowed */
+	Overflow,
+}
+
+/**
+ * A fault of the machine, it holds the instruction pointer and
+ * the raw instruction word of the instruction that failed.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Error {
+	pub ip: usize,
+	pub instruction: i64,
+	pub kind: ErrorKind,
+}
+
+impl fmt::Display for ErrorKind {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			ErrorKind::InvalidOpcode(code) => write!(f, "invalid opcode {}", code),
+			ErrorKind::InvalidMode(mode) => write!(f, "invalid parameter mode {}", mode),
+			ErrorKind::ImmediateWrite => write!(f, "write parameter in immediate mode"),
+			ErrorKind::NegativeAddress(address) => write!(f, "negative address {}", address),
+			ErrorKind::StepLimit(steps) => write!(f, "step limit of {} reached", steps),
+			ErrorKind::MemoryLimit(address) => {
+				write!(f, "address {} is past the memory limit", address)
+			}
+			ErrorKind::Deadline => write!(f, "deadline passed"),
+			ErrorKind::CodeWrite(address) => write!(f, "write to code at address {}", address),
+			ErrorKind::ReadOnly(address) => write!(f, "write to read-only address {}", address),
+			ErrorKind::Overflow => write!(f, "arithmetic overflow"),
+		}
+	}
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"{} at ip {} (instruction {})",
+			self.kind, self.ip, self.instruction
+		)
+	}
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,122 @@
+use crate::{decode, ErrorKind, OpMachine};
+use std::collections::HashSet;
+use std::ops::Range;
+
+/**
+ * What to do when a program writes to an address it executed as code.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Policy {
+	Allow,
+	/* record the write in the warnings of the guard */
+	Warn,
+	Fault,
+}
+
+/**
+ * A write to an address that was executed as code, ip is the
+ * instruction that wrote it, None for a write from outside.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CodeWrite {
+	pub ip: Option<usize>,
+	pub address: usize,
+	pub old: i64,
+	pub new: i64,
+}
+
+/**
+ * Watches the writes of a machine, it tracks which addresses were
+ * executed as instructions or their parameters, and faults writes
+ * to the read-only ranges.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Guard {
+	pub policy: Policy,
+	pub read_only: Vec<Range<usize>>,
+	pub warnings: Vec<CodeWrite>,
+	executed: HashSet<usize>,
+}
+
+impl Guard {
+	pub fn new(policy: Policy) -> Guard {
+		Guard {
+			policy,
+			read_only: Vec::new(),
+			warnings: Vec::new(),
+			executed: HashSet::new(),
+		}
+	}
+
+	pub fn with_read_only(mut self, range: Range<usize>) -> Guard {
+		self.read_only.push(range);
+		self
+	}
+
+	pub fn is_executed(&self, address: usize) -> bool {
+		self.executed.contains(&address)
+	}
+
+	/// Check a write of value over old at address by the instruction
+	/// at ip, or from outside when ip is None.
+	fn check(
+		&mut self,
+		ip: Option<usize>,
+		address: usize,
+		old: i64,
+		new: i64,
+	) -> Result<(), ErrorKind> {
+		if self.read_only.iter().any(|range| range.contains(&address)) {
+			return Err(ErrorKind::ReadOnly(address));
+		}
+
+		if !self.executed.contains(&address) {
+			return Ok(());
+		}
+
+		match self.policy {
+			Policy::Allow => {}
+			Policy::Warn => self.warnings.push(CodeWrite {
+				ip,
+				address,
+				old,
+				new,
+			}),
+			Policy::Fault => return Err(ErrorKind::CodeWrite(address)),
+		}
+
+		Ok(())
+	}
+}
+
+impl OpMachine {
+	/// Check a write against the guard, if there is one, ip is the
+	/// instruction that writes or None for a write from outside.
+	#[inline]
+	pub(crate) fn guard_write(
+		&mut self,
+		ip: Option<usize>,
+		address: usize,
+		value: i64,
+	) -> Result<(), ErrorKind> {
+		match &mut self.guard {
+			Some(guard) => guard.check(ip, address, self.program.get(address), value),
+			None => Ok(()),
+		}
+	}
+
+	/// Mark the instruction at ip and its parameters as executed.
+	pub(crate) fn guard_executed(&mut self, ip: usize, instruction: i64) {
+		if let (Some(guard), Ok((code, _))) = (&mut self.guard, decode(instruction)) {
+			guard.executed.extend(ip..ip + code.arity() + 1);
+		}
+	}
+
+	/// Write to memory from outside of the program, like patching it
+	/// before it runs, this is checked against the guard as well.
+	pub fn write(&mut self, address: usize, value: i64) -> Result<(), ErrorKind> {
+		self.guard_write(None, address, value)?;
+		self.program.set(address, value);
+		Ok(())
+	}
+}
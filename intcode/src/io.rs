@@ -0,0 +1,137 @@
+use crate::{OpMachine, RunState};
+use std::io::{self, BufRead};
+use std::sync::mpsc::{Receiver, Sender};
+
+/**
+ * Where a machine takes its inputs from and sends its outputs to,
+ * used by OpMachine::run_with.
+ */
+pub trait IntcodeIo {
+	/// The next input, None when there is none, which stops the machine.
+	fn input(&mut self) -> Option<i64>;
+
+	fn output(&mut self, value: i64);
+}
+
+/**
+ * Inputs and outputs handled by two closures.
+ */
+pub struct Callbacks<I, O> {
+	input: I,
+	output: O,
+}
+
+impl<I: FnMut() -> Option<i64>, O: FnMut(i64)> Callbacks<I, O> {
+	pub fn new(input: I, output: O) -> Callbacks<I, O> {
+		Callbacks { input, output }
+	}
+}
+
+impl<I: FnMut() -> Option<i64>, O: FnMut(i64)> IntcodeIo for Callbacks<I, O> {
+	fn input(&mut self) -> Option<i64> {
+		(self.input)()
+	}
+
+	fn output(&mut self, value: i64) {
+		(self.output)(value)
+	}
+}
+
+/**
+ * Inputs taken from an iterator, outputs collected.
+ */
+pub struct IterIo<I> {
+	inputs: I,
+	pub outputs: Vec<i64>,
+}
+
+impl<I: Iterator<Item = i64>> IterIo<I> {
+	pub fn new<T: IntoIterator<IntoIter = I, Item = i64>>(inputs: T) -> IterIo<I> {
+		IterIo {
+			inputs: inputs.into_iter(),
+			outputs: Vec::new(),
+		}
+	}
+}
+
+impl<I: Iterator<Item = i64>> IntcodeIo for IterIo<I> {
+	fn input(&mut self) -> Option<i64> {
+		self.inputs.next()
+	}
+
+	fn output(&mut self, value: i64) {
+		self.outputs.push(value);
+	}
+}
+
+/**
+ * Inputs received from and outputs sent to channels, so machines
+ * can run on their own threads. Waiting for input blocks until a
+ * value arrives, or until the sending side is gone.
+ */
+pub struct ChannelIo {
+	pub receiver: Receiver<i64>,
+	pub sender: Sender<i64>,
+}
+
+impl IntcodeIo for ChannelIo {
+	fn input(&mut self) -> Option<i64> {
+		self.receiver.recv().ok()
+	}
+
+	fn output(&mut self, value: i64) {
+		/* the receiving side may have stopped listening */
+		let _ = self.sender.send(value);
+	}
+}
+
+/**
+ * Reads a number per line from stdin and prints every output on a line.
+ */
+#[derive(Default)]
+pub struct StdIo;
+
+impl IntcodeIo for StdIo {
+	fn input(&mut self) -> Option<i64> {
+		let stdin = io::stdin();
+
+		loop {
+			let mut line = String::new();
+			if stdin.lock().read_line(&mut line).ok()? == 0 {
+				return None;
+			}
+
+			match line.trim().parse::<i64>() {
+				Ok(value) => return Some(value),
+				Err(_) => eprintln!("'{}' is not a number", line.trim()),
+			}
+		}
+	}
+
+	fn output(&mut self, value: i64) {
+		println!("{}", value);
+	}
+}
+
+impl OpMachine {
+	/// Run the machine with io for its inputs and outputs, until it
+	/// halts, faults, or io has no more input.
+	pub fn run_with<T: IntcodeIo + ?Sized>(&mut self, io: &mut T) -> RunState {
+		loop {
+			let state = self.step();
+			while let Some(output) = self.outputs.pop_front() {
+				io.output(output);
+			}
+
+			match state {
+				Ok(None) => {}
+				Ok(Some(RunState::NeedsInput)) => match io.input() {
+					Some(value) => self.register_input(value),
+					None => return RunState::NeedsInput,
+				},
+				Ok(Some(state)) => return state,
+				Err(error) => return RunState::Fault(error),
+			}
+		}
+	}
+}
@@ -0,0 +1,508 @@
+use std::collections::VecDeque;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+pub mod ascii;
+pub mod asm;
+pub mod batch;
+pub mod debugger;
+pub mod disasm;
+mod error;
+pub mod guard;
+pub mod io;
+mod limits;
+mod memory;
+pub mod network;
+pub mod pipeline;
+mod snapshot;
+pub mod symbolic;
+pub mod trace;
+
+pub use error::{Error, ErrorKind};
+use guard::Guard;
+pub use io::IntcodeIo;
+pub use limits::Limits;
+pub use memory::Memory;
+pub use snapshot::{ParseError, Snapshot};
+use trace::{Profile, Tracer};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OpCodes {
+	Add = 1,
+	Mul,
+	Input,
+	Output,
+	JIT,
+	JIF,
+	LT,
+	EQ,
+	RA,
+	Halt = 99,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OpModes {
+	Parametric = 0,
+	Immediate,
+	Relative,
+}
+
+impl OpCodes {
+	pub fn from(code: i64) -> Option<OpCodes> {
+		match code {
+			1 => Some(OpCodes::Add),
+			2 => Some(OpCodes::Mul),
+			3 => Some(OpCodes::Input),
+			4 => Some(OpCodes::Output),
+			5 => Some(OpCodes::JIT),
+			6 => Some(OpCodes::JIF),
+			7 => Some(OpCodes::LT),
+			8 => Some(OpCodes::EQ),
+			9 => Some(OpCodes::RA),
+			99 => Some(OpCodes::Halt),
+			_ => None,
+		}
+	}
+
+	pub fn param_count(&self) -> usize {
+		match self {
+			OpCodes::Add | OpCodes::Mul | OpCodes::LT | OpCodes::EQ => 4,
+			OpCodes::Input | OpCodes::Output | OpCodes::RA => 2,
+			_ => 0,
+		}
+	}
+
+	/// The number of parameters the instruction takes, unlike
+	/// param_count this is not zero for the jumps.
+	pub fn arity(&self) -> usize {
+		match self {
+			OpCodes::Add | OpCodes::Mul | OpCodes::LT | OpCodes::EQ => 3,
+			OpCodes::JIT | OpCodes::JIF => 2,
+			OpCodes::Input | OpCodes::Output | OpCodes::RA => 1,
+			OpCodes::Halt => 0,
+		}
+	}
+
+	pub fn mnemonic(&self) -> &'static str {
+		match self {
+			OpCodes::Add => "ADD",
+			OpCodes::Mul => "MUL",
+			OpCodes::Input => "IN",
+			OpCodes::Output => "OUT",
+			OpCodes::JIT => "JIT",
+			OpCodes::JIF => "JIF",
+			OpCodes::LT => "LT",
+			OpCodes::EQ => "EQ",
+			OpCodes::RA => "ARB",
+			OpCodes::Halt => "HLT",
+		}
+	}
+
+	pub fn from_mnemonic(mnemonic: &str) -> Option<OpCodes> {
+		let codes = [
+			OpCodes::Add,
+			OpCodes::Mul,
+			OpCodes::Input,
+			OpCodes::Output,
+			OpCodes::JIT,
+			OpCodes::JIF,
+			OpCodes::LT,
+			OpCodes::EQ,
+			OpCodes::RA,
+			OpCodes::Halt,
+		];
+
+		codes
+			.iter()
+			.find(|code| code.mnemonic().eq_ignore_ascii_case(mnemonic))
+			.copied()
+	}
+}
+
+impl OpModes {
+	pub fn from(input: i64) -> Option<OpModes> {
+		match input % 10 {
+			0 => Some(OpModes::Parametric),
+			1 => Some(OpModes::Immediate),
+			2 => Some(OpModes::Relative),
+			_ => None,
+		}
+	}
+}
+
+/* every instruction word with a valid opcode and modes is below this */
+const DECODED_SIZE: usize = 22300;
+
+type Decoded = Result<(OpCodes, [OpModes; 3]), ErrorKind>;
+
+fn decode_word(instruction: i64) -> Decoded {
+	let code = match OpCodes::from(instruction % 100) {
+		Some(code) => code,
+		None => return Err(ErrorKind::InvalidOpcode(instruction % 100)),
+	};
+
+	let mut modes = [OpModes::Parametric; 3];
+	let mut digits = instruction / 100;
+	for mode in modes.iter_mut().take(code.arity()) {
+		*mode = match OpModes::from(digits) {
+			Some(mode) => mode,
+			None => return Err(ErrorKind::InvalidMode(digits % 10)),
+		};
+		digits /= 10;
+	}
+
+	Ok((code, modes))
+}
+
+/**
+ * Decode an instruction word in its opcode and the modes of its three
+ * parameters, the modes of parameters the opcode does not take are
+ * left as Parametric.
+ *
+ * The words that can be valid instructions are decoded once, and
+ * looked up in a table after that.
+ */
+#[inline]
+pub fn decode(instruction: i64) -> Result<(OpCodes, [OpModes; 3]), ErrorKind> {
+	static DECODED: OnceLock<Vec<Decoded>> = OnceLock::new();
+
+	if instruction < 0 || instruction as usize >= DECODED_SIZE {
+		return decode_word(instruction);
+	}
+
+	let decoded = DECODED.get_or_init(|| (0..DECODED_SIZE as i64).map(decode_word).collect());
+	decoded[instruction as usize]
+}
+
+/**
+ * Parse the comma separated program text, as found in the input files.
+ */
+pub fn parse(input: &str) -> Vec<i64> {
+	input
+		.trim()
+		.split(',')
+		.map(|num| num.trim().parse::<i64>().unwrap())
+		.collect()
+}
+
+/**
+ * The reason a machine stopped running.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunState {
+	Halted,
+	NeedsInput,
+	Output(i64),
+	Fault(Error),
+}
+
+impl RunState {
+	pub fn output(&self) -> Option<i64> {
+		match self {
+			RunState::Output(value) => Some(*value),
+			_ => None,
+		}
+	}
+}
+
+/**
+ * OpMachine, it handles the operations step by step.
+ * It uses the OpCodes struct above.
+ *
+ * When the machine wants input but there is none registered,
+ * it stops without moving the instruction pointer, so it can
+ * be resumed once input has been registered. Instead of registering
+ * inputs and taking outputs by hand, run_with hands them to an IntcodeIo.
+ *
+ * Tracing and profiling are off unless trace_to or enable_profile
+ * is called, see the trace module. Writes are only checked against
+ * code and read-only memory when a guard is set.
+ */
+#[derive(Clone)]
+pub struct OpMachine {
+	pub ip: usize,
+	pub program: Memory,
+	pub inputs: VecDeque<i64>,
+	pub outputs: VecDeque<i64>,
+	pub base: i64,
+	pub limits: Limits,
+	/* the number of instructions executed */
+	pub steps: u64,
+	pub guard: Option<Guard>,
+	pub profile: Option<Profile>,
+	tracer: Option<Tracer>,
+}
+
+impl OpMachine {
+	pub fn new<M: Into<Memory>>(program: M) -> OpMachine {
+		OpMachine {
+			ip: 0,
+			program: program.into(),
+			inputs: VecDeque::new(),
+			outputs: VecDeque::new(),
+			base: 0,
+			limits: Limits::default(),
+			steps: 0,
+			guard: None,
+			profile: None,
+			tracer: None,
+		}
+	}
+
+	#[inline]
+	fn read(&self, address: i64) -> Result<i64, ErrorKind> {
+		if address < 0 {
+			return Err(ErrorKind::NegativeAddress(address));
+		}
+
+		self.check_memory(address as usize)?;
+		Ok(self.program.get(address as usize))
+	}
+
+	#[inline]
+	fn check_memory(&self, address: usize) -> Result<(), ErrorKind> {
+		match self.limits.memory {
+			Some(cells) if address >= cells => Err(ErrorKind::MemoryLimit(address)),
+			_ => Ok(()),
+		}
+	}
+
+	/// The deadline is only checked every 1024 steps, asking
+	/// the time on every step would slow the machine down.
+	fn check_limits(&self) -> Result<(), ErrorKind> {
+		if let Some(steps) = self.limits.steps {
+			if self.steps >= steps {
+				return Err(ErrorKind::StepLimit(steps));
+			}
+		}
+
+		if let Some(deadline) = self.limits.deadline {
+			if self.steps.is_multiple_of(1024) && Instant::now() >= deadline {
+				return Err(ErrorKind::Deadline);
+			}
+		}
+
+		Ok(())
+	}
+
+	#[inline]
+	fn relative(&self, offset: i64) -> Result<i64, ErrorKind> {
+		self.base.checked_add(offset).ok_or(ErrorKind::Overflow)
+	}
+
+	#[inline]
+	fn get_value(&self, mode: &OpModes, position: usize) -> Result<i64, ErrorKind> {
+		let param = self.program.get(position);
+
+		match mode {
+			OpModes::Immediate => Ok(param),
+			OpModes::Relative => self.read(self.relative(param)?),
+			OpModes::Parametric => self.read(param),
+		}
+	}
+
+	#[inline]
+	fn store(&mut self, value: i64, position: usize, mode: &OpModes) -> Result<(), ErrorKind> {
+		let param = self.program.get(position);
+		let loc = match mode {
+			OpModes::Relative => self.relative(param)?,
+			OpModes::Parametric => param,
+			OpModes::Immediate => return Err(ErrorKind::ImmediateWrite),
+		};
+
+		if loc < 0 {
+			return Err(ErrorKind::NegativeAddress(loc));
+		}
+
+		self.check_memory(loc as usize)?;
+		self.guard_write(Some(self.ip), loc as usize, value)?;
+		self.program.set(loc as usize, value);
+		Ok(())
+	}
+
+	fn add(&mut self, modes: [OpModes; 3]) -> Result<(), ErrorKind> {
+		let verb = self.get_value(&modes[0], self.ip + 1)?;
+		let noun = self.get_value(&modes[1], self.ip + 2)?;
+		let sum = verb.checked_add(noun).ok_or(ErrorKind::Overflow)?;
+		self.store(sum, self.ip + 3, &modes[2])
+	}
+
+	fn mul(&mut self, modes: [OpModes; 3]) -> Result<(), ErrorKind> {
+		let verb = self.get_value(&modes[0], self.ip + 1)?;
+		let noun = self.get_value(&modes[1], self.ip + 2)?;
+		let product = verb.checked_mul(noun).ok_or(ErrorKind::Overflow)?;
+		self.store(product, self.ip + 3, &modes[2])
+	}
+
+	fn output(&mut self, mode: &OpModes) -> Result<(), ErrorKind> {
+		let verb = self.get_value(mode, self.ip + 1)?;
+		self.outputs.push_back(verb);
+		Ok(())
+	}
+
+	fn input(&mut self, mode: &OpModes) -> Result<Option<RunState>, ErrorKind> {
+		let value = match self.inputs.front() {
+			Some(value) => *value,
+			None => return Ok(Some(RunState::NeedsInput)),
+		};
+
+		self.store(value, self.ip + 1, mode)?;
+		self.inputs.pop_front();
+		Ok(None)
+	}
+
+	fn jump(&mut self, target: i64) -> Result<(), ErrorKind> {
+		if target < 0 {
+			return Err(ErrorKind::NegativeAddress(target));
+		}
+
+		self.ip = target as usize;
+		Ok(())
+	}
+
+	fn jump_if_true(&mut self, modes: [OpModes; 3]) -> Result<(), ErrorKind> {
+		let verb = self.get_value(&modes[0], self.ip + 1)?;
+		let noun = self.get_value(&modes[1], self.ip + 2)?;
+
+		if verb != 0 {
+			self.jump(noun)
+		} else {
+			self.ip += 3;
+			Ok(())
+		}
+	}
+
+	fn jump_if_false(&mut self, modes: [OpModes; 3]) -> Result<(), ErrorKind> {
+		let verb = self.get_value(&modes[0], self.ip + 1)?;
+		let noun = self.get_value(&modes[1], self.ip + 2)?;
+
+		if verb == 0 {
+			self.jump(noun)
+		} else {
+			self.ip += 3;
+			Ok(())
+		}
+	}
+
+	fn less_than(&mut self, modes: [OpModes; 3]) -> Result<(), ErrorKind> {
+		let verb = self.get_value(&modes[0], self.ip + 1)?;
+		let noun = self.get_value(&modes[1], self.ip + 2)?;
+		self.store((verb < noun) as i64, self.ip + 3, &modes[2])
+	}
+
+	fn equal(&mut self, modes: [OpModes; 3]) -> Result<(), ErrorKind> {
+		let verb = self.get_value(&modes[0], self.ip + 1)?;
+		let noun = self.get_value(&modes[1], self.ip + 2)?;
+		self.store((verb == noun) as i64, self.ip + 3, &modes[2])
+	}
+
+	fn relative_add(&mut self, modes: [OpModes; 3]) -> Result<(), ErrorKind> {
+		let verb = self.get_value(&modes[0], self.ip + 1)?;
+		self.base = self.relative(verb)?;
+		Ok(())
+	}
+
+	pub fn register_input(&mut self, input: i64) {
+		self.inputs.push_back(input);
+	}
+
+	pub fn has_output(&self) -> bool {
+		!self.outputs.is_empty()
+	}
+
+	#[inline]
+	fn execute(&mut self, instruction: i64) -> Result<Option<RunState>, ErrorKind> {
+		let (code, modes) = decode(instruction)?;
+
+		match code {
+			OpCodes::Add => self.add(modes)?,
+			OpCodes::Mul => self.mul(modes)?,
+			OpCodes::Input => {
+				if let Some(state) = self.input(&modes[0])? {
+					return Ok(Some(state));
+				}
+			}
+			OpCodes::Output => self.output(&modes[0])?,
+			OpCodes::JIT => self.jump_if_true(modes)?,
+			OpCodes::JIF => self.jump_if_false(modes)?,
+			OpCodes::LT => self.less_than(modes)?,
+			OpCodes::EQ => self.equal(modes)?,
+			OpCodes::RA => self.relative_add(modes)?,
+			OpCodes::Halt => return Ok(Some(RunState::Halted)),
+		}
+
+		self.ip += code.param_count();
+		Ok(None)
+	}
+
+	/// Execute a single instruction, returns the state the machine
+	/// stopped in, or None when it can keep on running.
+	/// A faulting instruction leaves the machine untouched.
+	pub fn step(&mut self) -> Result<Option<RunState>, Error> {
+		let ip = self.ip;
+		let instruction = self.program.get(ip);
+		let error = |kind| Error {
+			ip,
+			instruction,
+			kind,
+		};
+
+		self.check_limits().map_err(error)?;
+
+		let event = if self.tracer.is_none() && self.profile.is_none() {
+			None
+		} else {
+			self.event()
+		};
+
+		let state = self.execute(instruction).map_err(error)?;
+		if state != Some(RunState::NeedsInput) {
+			self.steps += 1;
+
+			if self.guard.is_some() {
+				self.guard_executed(ip, instruction);
+			}
+		}
+
+		if let (Some(event), None | Some(RunState::Halted)) = (event, state) {
+			self.record(&event);
+		}
+
+		Ok(state)
+	}
+
+	/// Execute up to steps instructions, outputs are queued instead
+	/// of stopping the machine. Returns the state the machine stopped
+	/// in, or None when all steps were executed.
+	pub fn run_for(&mut self, steps: usize) -> Option<RunState> {
+		for _ in 0..steps {
+			match self.step() {
+				Ok(None) => {}
+				Ok(Some(state)) => return Some(state),
+				Err(error) => return Some(RunState::Fault(error)),
+			}
+		}
+
+		None
+	}
+
+	pub fn run_until_output(&mut self) -> RunState {
+		loop {
+			match self.step() {
+				Ok(Some(state)) => return state,
+				Err(error) => return RunState::Fault(error),
+				Ok(None) => {}
+			}
+
+			if let Some(output) = self.outputs.pop_front() {
+				return RunState::Output(output);
+			}
+		}
+	}
+
+	pub fn run(&mut self) {
+		while let RunState::Output(output) = self.run_until_output() {
+			println!("{}", output);
+		}
+	}
+}
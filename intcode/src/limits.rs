@@ -0,0 +1,32 @@
+use std::time::{Duration, Instant};
+
+/**
+ * Limits on what a machine may do, a machine that hits one faults
+ * with StepLimit, MemoryLimit or Deadline. There are none by default.
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Limits {
+	/* the number of instructions the machine may execute */
+	pub steps: Option<u64>,
+	/* the number of memory cells, addresses from here on can not be used */
+	pub memory: Option<usize>,
+	pub deadline: Option<Instant>,
+}
+
+impl Limits {
+	pub fn with_steps(mut self, steps: u64) -> Limits {
+		self.steps = Some(steps);
+		self
+	}
+
+	pub fn with_memory(mut self, cells: usize) -> Limits {
+		self.memory = Some(cells);
+		self
+	}
+
+	/// A deadline the timeout from now.
+	pub fn with_timeout(mut self, timeout: Duration) -> Limits {
+		self.deadline = Some(Instant::now() + timeout);
+		self
+	}
+}
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::ops::{Index, IndexMut};
+use std::sync::Arc;
+
+/* writes up to this far past the end grow the dense cells, further ones are kept sparse */
+const GROW_LIMIT: usize = 1 << 16;
+
+const PAGE_BITS: usize = 8;
+const PAGE_SIZE: usize = 1 << PAGE_BITS;
+
+type Page = [i64; PAGE_SIZE];
+
+/**
+ * The page to write to, a page no clone holds is written in place,
+ * make_mut only copies one that is shared. Checking with get_mut
+ * first would cost a second atomic check on every write.
+ */
+#[inline]
+fn page_mut(page: &mut Arc<Page>) -> &mut Page {
+	Arc::make_mut(page)
+}
+
+static ZERO: i64 = 0;
+
+/**
+ * Memory of the machine, every address reads as zero until it is written.
+ * The program is stored densely and grows on write, writes far away
+ * from the program end up in a map so a large address does not
+ * allocate everything in between.
+ *
+ * The dense cells are split in pages that are shared between clones,
+ * a page is only copied once a clone writes to it. That makes cloning
+ * a machine cheap, even when thousands of them are branched off.
+ * Writing to a page no clone shares is a plain store.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Memory {
+	pages: Vec<Arc<Page>>,
+	len: usize,
+	sparse: Arc<HashMap<usize, i64>>,
+}
+
+impl Memory {
+	pub fn new(cells: Vec<i64>) -> Memory {
+		let pages = cells
+			.chunks(PAGE_SIZE)
+			.map(|chunk| {
+				let mut page = [0; PAGE_SIZE];
+				page[..chunk.len()].copy_from_slice(chunk);
+				Arc::new(page)
+			})
+			.collect();
+
+		Memory {
+			pages,
+			len: cells.len(),
+			sparse: Arc::new(HashMap::new()),
+		}
+	}
+
+	/// Memory with the dense cells and the cells written far past them.
+	pub fn with_sparse(cells: Vec<i64>, sparse: HashMap<usize, i64>) -> Memory {
+		let mut memory = Memory::new(cells);
+		let (dense, sparse): (HashMap<usize, i64>, HashMap<usize, i64>) = sparse
+			.into_iter()
+			.partition(|(address, _)| *address < memory.len);
+
+		for (address, value) in dense {
+			memory.set(address, value);
+		}
+
+		memory.sparse = Arc::new(sparse);
+		memory
+	}
+
+	#[inline]
+	pub fn get(&self, address: usize) -> i64 {
+		self[address]
+	}
+
+	#[inline]
+	pub fn set(&mut self, address: usize, value: i64) {
+		self[address] = value;
+	}
+
+	/// The size of the dense part of memory, sparse cells are not counted.
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len == 0 && self.sparse.is_empty()
+	}
+
+	/// A copy of the dense part of memory, as it was loaded plus the growth.
+	pub fn to_vec(&self) -> Vec<i64> {
+		let mut cells: Vec<i64> = self
+			.pages
+			.iter()
+			.flat_map(|page| page.iter().copied())
+			.collect();
+		cells.truncate(self.len);
+		cells
+	}
+
+	/// The cells written outside of the dense part, in no particular order.
+	pub fn sparse(&self) -> impl Iterator<Item = (usize, i64)> + '_ {
+		self.sparse
+			.iter()
+			.map(|(address, value)| (*address, *value))
+	}
+
+	fn grow(&mut self, size: usize) {
+		while self.pages.len() * PAGE_SIZE < size {
+			self.pages.push(Arc::new([0; PAGE_SIZE]));
+		}
+		self.len = size;
+
+		if self.sparse.keys().any(|address| *address < size) {
+			let sparse = Arc::make_mut(&mut self.sparse);
+			let moved: Vec<usize> = sparse
+				.keys()
+				.filter(|address| **address < size)
+				.copied()
+				.collect();
+
+			for address in moved {
+				let value = sparse.remove(&address).unwrap();
+				page_mut(&mut self.pages[address >> PAGE_BITS])[address & (PAGE_SIZE - 1)] = value;
+			}
+		}
+	}
+}
+
+impl From<Vec<i64>> for Memory {
+	fn from(cells: Vec<i64>) -> Memory {
+		Memory::new(cells)
+	}
+}
+
+impl Index<usize> for Memory {
+	type Output = i64;
+
+	#[inline]
+	fn index(&self, address: usize) -> &i64 {
+		if address < self.len {
+			return &self.pages[address >> PAGE_BITS][address & (PAGE_SIZE - 1)];
+		}
+
+		self.sparse.get(&address).unwrap_or(&ZERO)
+	}
+}
+
+impl IndexMut<usize> for Memory {
+	#[inline]
+	fn index_mut(&mut self, address: usize) -> &mut i64 {
+		if address >= self.len {
+			if address - self.len >= GROW_LIMIT {
+				return Arc::make_mut(&mut self.sparse).entry(address).or_insert(0);
+			}
+
+			self.grow(address + 1);
+		}
+
+		&mut page_mut(&mut self.pages[address >> PAGE_BITS])[address & (PAGE_SIZE - 1)]
+	}
+}
@@ -0,0 +1,240 @@
+use crate::{Error, OpMachine, RunState};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Write;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Packet {
+	pub from: i64,
+	pub to: i64,
+	pub x: i64,
+	pub y: i64,
+}
+
+impl fmt::Display for Packet {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{} -> {}: x {} y {}", self.from, self.to, self.x, self.y)
+	}
+}
+
+/**
+ * What a device wants the network to do.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+	Nothing,
+	Send(Packet),
+	/* stop the network with the answer */
+	Stop(i64),
+}
+
+/**
+ * A device at a special address, like the NAT at 255.
+ */
+pub trait Device {
+	fn receive(&mut self, packet: Packet) -> Action;
+
+	/// Called every round in which the whole network is idle.
+	fn idle(&mut self) -> Action {
+		Action::Nothing
+	}
+}
+
+/**
+ * A computer on the network, it booted with its address as first input.
+ */
+pub struct Node {
+	pub machine: OpMachine,
+	/* the number of -1 reads since it last sent or received a packet */
+	empty_reads: usize,
+	outputs: Vec<i64>,
+	halted: bool,
+}
+
+impl Node {
+	/// A node is idle once it has read -1 several times in a row,
+	/// and no packet is waiting for it.
+	fn is_idle(&self, reads: usize) -> bool {
+		self.halted || (self.empty_reads >= reads && self.machine.inputs.is_empty())
+	}
+}
+
+/**
+ * A network of machines running the same program, scheduled round robin.
+ * A node that wants input while none is queued reads -1. Packets are
+ * routed by address to a node or to a device attached at that address,
+ * packets to other addresses are dropped.
+ */
+pub struct Network {
+	pub nodes: Vec<Node>,
+	/* consecutive -1 reads after which a node counts as idle */
+	pub idle_reads: usize,
+	devices: BTreeMap<i64, Box<dyn Device>>,
+	log: Option<Box<dyn Write>>,
+}
+
+impl Network {
+	pub fn new(program: &[i64], count: usize) -> Network {
+		let nodes = (0..count)
+			.map(|address| {
+				let mut machine = OpMachine::new(program.to_vec());
+				machine.register_input(address as i64);
+
+				Node {
+					machine,
+					empty_reads: 0,
+					outputs: Vec::new(),
+					halted: false,
+				}
+			})
+			.collect();
+
+		Network {
+			nodes,
+			idle_reads: 2,
+			devices: BTreeMap::new(),
+			log: None,
+		}
+	}
+
+	pub fn attach<D: Device + 'static>(&mut self, address: i64, device: D) {
+		self.devices.insert(address, Box::new(device));
+	}
+
+	/// Write every packet that is sent to the writer.
+	pub fn log_to<W: Write + 'static>(&mut self, writer: W) {
+		self.log = Some(Box::new(writer));
+	}
+
+	fn log(&mut self, packet: &Packet, note: &str) {
+		if let Some(log) = &mut self.log {
+			/* a failing log should not stop the network */
+			let _ = writeln!(log, "{}{}", packet, note);
+		}
+	}
+
+	/// Deliver the packet, Some when a device stopped the network.
+	pub fn send(&mut self, packet: Packet) -> Option<i64> {
+		if packet.to >= 0 && (packet.to as usize) < self.nodes.len() {
+			self.log(&packet, "");
+			let node = &mut self.nodes[packet.to as usize];
+			node.machine.register_input(packet.x);
+			node.machine.register_input(packet.y);
+			node.empty_reads = 0;
+			return None;
+		}
+
+		let action = match self.devices.get_mut(&packet.to) {
+			Some(device) => device.receive(packet),
+			None => {
+				self.log(&packet, " (dropped)");
+				return None;
+			}
+		};
+
+		self.log(&packet, "");
+		self.act(action)
+	}
+
+	fn act(&mut self, action: Action) -> Option<i64> {
+		match action {
+			Action::Nothing => None,
+			Action::Send(packet) => self.send(packet),
+			Action::Stop(answer) => Some(answer),
+		}
+	}
+
+	/// Run the node until it wants input again, and send its packets.
+	fn turn(&mut self, address: usize) -> Result<Option<i64>, Error> {
+		let mut packets = Vec::new();
+		let node = &mut self.nodes[address];
+		if node.halted {
+			return Ok(None);
+		}
+
+		if node.machine.inputs.is_empty() {
+			node.machine.register_input(-1);
+			node.empty_reads += 1;
+		}
+
+		loop {
+			match node.machine.run_until_output() {
+				RunState::Output(value) => node.outputs.push(value),
+				RunState::NeedsInput => break,
+				RunState::Halted => {
+					node.halted = true;
+					break;
+				}
+				RunState::Fault(error) => {
+					node.halted = true;
+					return Err(error);
+				}
+			}
+
+			if let [to, x, y] = node.outputs[..] {
+				packets.push(Packet {
+					from: address as i64,
+					to,
+					x,
+					y,
+				});
+				node.outputs.clear();
+				node.empty_reads = 0;
+			}
+		}
+
+		Ok(packets.into_iter().find_map(|packet| self.send(packet)))
+	}
+
+	pub fn is_idle(&self) -> bool {
+		self.nodes.iter().all(|node| node.is_idle(self.idle_reads))
+	}
+
+	/// Give every node a turn, and the devices a chance to act when
+	/// the network is idle afterwards. A fault of a node is returned.
+	pub fn round(&mut self) -> Result<Option<i64>, Error> {
+		for address in 0..self.nodes.len() {
+			if let Some(answer) = self.turn(address)? {
+				return Ok(Some(answer));
+			}
+		}
+
+		if !self.is_idle() {
+			return Ok(None);
+		}
+
+		let addresses: Vec<i64> = self.devices.keys().copied().collect();
+		for address in addresses {
+			let action = self.devices.get_mut(&address).unwrap().idle();
+			if let Some(answer) = self.act(action) {
+				return Ok(Some(answer));
+			}
+		}
+
+		Ok(None)
+	}
+
+	/// Run until a device stops the network, None when all nodes halted
+	/// or the network stays idle without a device waking it up, the
+	/// fault of a node stops it as well.
+	pub fn run(&mut self) -> Result<Option<i64>, Error> {
+		loop {
+			if let Some(answer) = self.round()? {
+				return Ok(Some(answer));
+			}
+
+			if self.nodes.iter().all(|node| node.halted) {
+				return Ok(None);
+			}
+
+			/* still idle a round later, so no device woke it up */
+			if self
+				.nodes
+				.iter()
+				.all(|node| node.is_idle(self.idle_reads + 1))
+			{
+				return Ok(None);
+			}
+		}
+	}
+}
@@ -0,0 +1,141 @@
+use crate::{Error, OpMachine, RunState};
+
+/**
+ * When a pipeline stops running, besides when no machine can
+ * make any progress anymore.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Until {
+	AllHalted,
+	AnyHalted,
+	/* the output machine produced this many outputs */
+	Outputs(usize),
+}
+
+/**
+ * Machines connected by their outputs, in series, in a ring or in any
+ * other directed graph. Every output of a machine is sent to all the
+ * machines it is connected to, the outputs of the output machine (the
+ * last one unless set) are the outputs of the pipeline.
+ *
+ *   let outputs = Pipeline::new(&program, &[9, 8, 7, 6, 5])
+ *       .ring()
+ *       .seed(0, 0)
+ *       .run()?;
+ */
+pub struct Pipeline {
+	machines: Vec<OpMachine>,
+	edges: Vec<Vec<usize>>,
+	output: usize,
+	until: Until,
+}
+
+impl Pipeline {
+	/// A machine running the program for every phase, which is its first input.
+	pub fn new(program: &[i64], phases: &[i64]) -> Pipeline {
+		let template = OpMachine::new(program.to_vec());
+		let machines = phases
+			.iter()
+			.map(|phase| {
+				let mut machine = template.fork();
+				machine.register_input(*phase);
+				machine
+			})
+			.collect();
+
+		Pipeline::from_machines(machines)
+	}
+
+	pub fn from_machines(machines: Vec<OpMachine>) -> Pipeline {
+		Pipeline {
+			edges: vec![Vec::new(); machines.len()],
+			output: machines.len().saturating_sub(1),
+			machines,
+			until: Until::AllHalted,
+		}
+	}
+
+	/// Send the outputs of from to to.
+	pub fn connect(mut self, from: usize, to: usize) -> Pipeline {
+		self.edges[from].push(to);
+		self
+	}
+
+	/// Connect every machine to the next one.
+	pub fn series(mut self) -> Pipeline {
+		for i in 1..self.machines.len() {
+			self = self.connect(i - 1, i);
+		}
+
+		self
+	}
+
+	/// Connect the machines in series, and the last one back to the first.
+	pub fn ring(self) -> Pipeline {
+		let last = self.machines.len() - 1;
+		self.series().connect(last, 0)
+	}
+
+	/// Give the machine an input before the pipeline starts.
+	pub fn seed(mut self, machine: usize, value: i64) -> Pipeline {
+		self.machines[machine].register_input(value);
+		self
+	}
+
+	/// Take the outputs of the pipeline from this machine.
+	pub fn output(mut self, machine: usize) -> Pipeline {
+		self.output = machine;
+		self
+	}
+
+	pub fn until(mut self, until: Until) -> Pipeline {
+		self.until = until;
+		self
+	}
+
+	/// Run the machines in turn, each until it needs input, and return the
+	/// outputs of the output machine. Stops on the termination condition,
+	/// or when no machine can continue, a fault of any machine is returned.
+	pub fn run(&mut self) -> Result<Vec<i64>, Error> {
+		let mut halted = vec![false; self.machines.len()];
+		let mut outputs = Vec::new();
+
+		loop {
+			let mut progress = false;
+
+			for (i, halted) in halted.iter_mut().enumerate() {
+				while !*halted {
+					let output = match self.machines[i].run_until_output() {
+						RunState::Output(output) => output,
+						RunState::NeedsInput => break,
+						RunState::Halted => {
+							*halted = true;
+							break;
+						}
+						RunState::Fault(error) => return Err(error),
+					};
+
+					progress = true;
+					for to in &self.edges[i] {
+						self.machines[*to].register_input(output);
+					}
+
+					if i == self.output {
+						outputs.push(output);
+						if self.until == Until::Outputs(outputs.len()) {
+							return Ok(outputs);
+						}
+					}
+				}
+
+				if self.until == Until::AnyHalted && *halted {
+					return Ok(outputs);
+				}
+			}
+
+			if !progress || halted.iter().all(|halted| *halted) {
+				return Ok(outputs);
+			}
+		}
+	}
+}
@@ -0,0 +1,225 @@
+use crate::{Memory, OpMachine};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+/**
+ * The complete state of a machine: instruction pointer, relative base,
+ * memory and the inputs and outputs that are still queued.
+ * Memory is shared with the machine until either one writes to it,
+ * so taking a snapshot is cheap. It can be saved to and loaded from
+ * a text file, see the Display implementation for the format.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Snapshot {
+	pub ip: usize,
+	pub base: i64,
+	pub memory: Memory,
+	pub inputs: VecDeque<i64>,
+	pub outputs: VecDeque<i64>,
+}
+
+impl OpMachine {
+	pub fn snapshot(&self) -> Snapshot {
+		Snapshot {
+			ip: self.ip,
+			base: self.base,
+			memory: self.program.clone(),
+			inputs: self.inputs.clone(),
+			outputs: self.outputs.clone(),
+		}
+	}
+
+	/// Put the machine back in the state of the snapshot,
+	/// tracing and profiling are left as they are.
+	pub fn restore(&mut self, snapshot: &Snapshot) {
+		self.ip = snapshot.ip;
+		self.base = snapshot.base;
+		self.program = snapshot.memory.clone();
+		self.inputs = snapshot.inputs.clone();
+		self.outputs = snapshot.outputs.clone();
+	}
+
+	/// A new machine that continues from the current state, the
+	/// two share memory until one of them writes to it.
+	pub fn fork(&self) -> OpMachine {
+		self.clone()
+	}
+}
+
+impl From<&Snapshot> for OpMachine {
+	fn from(snapshot: &Snapshot) -> OpMachine {
+		let mut machine = OpMachine::new(Memory::default());
+		machine.restore(snapshot);
+		machine
+	}
+}
+
+/**
+ * A snapshot that could not be read, with the (1 based) line it failed on.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+	pub line: usize,
+	pub message: String,
+}
+
+impl fmt::Display for ParseError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "line {}: {}", self.line, self.message)
+	}
+}
+
+impl std::error::Error for ParseError {}
+
+const HEADER: &str = "intcode snapshot 1";
+
+/// A line of the text form, the name followed by the comma separated values.
+fn field<I: Iterator<Item = String>>(name: &str, values: I) -> String {
+	let values: Vec<String> = values.collect();
+	if values.is_empty() {
+		return name.to_string();
+	}
+
+	format!("{} {}", name, values.join(","))
+}
+
+fn numbers<T: FromStr>(text: &str) -> Result<Vec<T>, String> {
+	text.split(',')
+		.filter(|number| !number.trim().is_empty())
+		.map(|number| {
+			number
+				.trim()
+				.parse::<T>()
+				.map_err(|_| format!("invalid number '{}'", number.trim()))
+		})
+		.collect()
+}
+
+/**
+ * The text form of a snapshot, one field on a line:
+ *   intcode snapshot 1
+ *   ip 12
+ *   base 0
+ *   inputs 1,2
+ *   outputs
+ *   memory 109,1,204,-1
+ *   sparse 100000=5
+ * The sparse line holds the cells written far past the end of memory.
+ */
+impl fmt::Display for Snapshot {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let mut sparse: Vec<(usize, i64)> = self.memory.sparse().collect();
+		sparse.sort_unstable();
+
+		writeln!(f, "{}", HEADER)?;
+		writeln!(f, "ip {}", self.ip)?;
+		writeln!(f, "base {}", self.base)?;
+		writeln!(
+			f,
+			"{}",
+			field("inputs", self.inputs.iter().map(|x| x.to_string()))
+		)?;
+		writeln!(
+			f,
+			"{}",
+			field("outputs", self.outputs.iter().map(|x| x.to_string()))
+		)?;
+		writeln!(
+			f,
+			"{}",
+			field("memory", self.memory.to_vec().iter().map(|x| x.to_string()))
+		)?;
+		let cells = sparse.iter().map(|(a, v)| format!("{}={}", a, v));
+		writeln!(f, "{}", field("sparse", cells))
+	}
+}
+
+impl FromStr for Snapshot {
+	type Err = ParseError;
+
+	fn from_str(text: &str) -> Result<Snapshot, ParseError> {
+		let mut lines = text.lines().enumerate();
+		match lines.next() {
+			Some((_, HEADER)) => {}
+			_ => {
+				return Err(ParseError {
+					line: 1,
+					message: format!("expected '{}'", HEADER),
+				})
+			}
+		}
+
+		let mut snapshot = Snapshot::default();
+		let mut cells: Vec<i64> = Vec::new();
+		let mut sparse: HashMap<usize, i64> = HashMap::new();
+
+		for (i, line) in lines {
+			let error = |message: String| ParseError {
+				line: i + 1,
+				message,
+			};
+			let line = line.trim();
+			if line.is_empty() {
+				continue;
+			}
+
+			let (key, value) = match line.find(' ') {
+				Some(space) => (&line[..space], &line[space + 1..]),
+				None => (line, ""),
+			};
+
+			match key {
+				"ip" => {
+					snapshot.ip = value
+						.parse()
+						.map_err(|_| error(format!("invalid ip '{}'", value)))?
+				}
+				"base" => {
+					snapshot.base = value
+						.parse()
+						.map_err(|_| error(format!("invalid base '{}'", value)))?
+				}
+				"inputs" => snapshot.inputs = numbers(value).map_err(error)?.into_iter().collect(),
+				"outputs" => {
+					snapshot.outputs = numbers(value).map_err(error)?.into_iter().collect()
+				}
+				"memory" => cells = numbers(value).map_err(error)?,
+				"sparse" => {
+					for cell in value.split(',').filter(|cell| !cell.trim().is_empty()) {
+						let parsed = match cell.trim().split_once('=') {
+							Some((address, value)) => address.parse().ok().zip(value.parse().ok()),
+							None => None,
+						};
+
+						match parsed {
+							Some((address, value)) => sparse.insert(address, value),
+							None => {
+								return Err(error(format!("invalid sparse cell '{}'", cell.trim())))
+							}
+						};
+					}
+				}
+				_ => return Err(error(format!("unknown field '{}'", key))),
+			}
+		}
+
+		snapshot.memory = Memory::with_sparse(cells, sparse);
+		Ok(snapshot)
+	}
+}
+
+impl Snapshot {
+	pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+		fs::write(path, self.to_string())
+	}
+
+	pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Snapshot> {
+		fs::read_to_string(path)?
+			.parse()
+			.map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+	}
+}
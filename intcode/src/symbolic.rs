@@ -0,0 +1,376 @@
+use crate::{decode, OpCodes, OpModes};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+/* a program that runs longer than this is taken to loop forever */
+const MAX_STEPS: usize = 1_000_000;
+
+/**
+ * A polynomial with integer coefficients, every term is keyed by
+ * the sorted names of the variables multiplied in it.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Poly {
+	terms: BTreeMap<Vec<String>, i64>,
+}
+
+impl Poly {
+	pub fn constant(value: i64) -> Poly {
+		let mut poly = Poly::default();
+		if value != 0 {
+			poly.terms.insert(vec![], value);
+		}
+
+		poly
+	}
+
+	pub fn var(name: &str) -> Poly {
+		let mut poly = Poly::default();
+		poly.terms.insert(vec![name.to_string()], 1);
+		poly
+	}
+
+	/// The value of the polynomial, when it has no variables.
+	pub fn as_constant(&self) -> Option<i64> {
+		match self.terms.iter().next() {
+			None => Some(0),
+			Some((names, value)) if names.is_empty() && self.terms.len() == 1 => Some(*value),
+			_ => None,
+		}
+	}
+
+	/// The highest number of variables multiplied in a term.
+	pub fn degree(&self) -> usize {
+		self.terms
+			.keys()
+			.map(|names| names.len())
+			.max()
+			.unwrap_or(0)
+	}
+
+	/// Add value to the coefficient of the term, None when it overflows.
+	fn insert(&mut self, names: Vec<String>, value: i64) -> Option<()> {
+		let coefficient = self.terms.entry(names.clone()).or_insert(0);
+		*coefficient = coefficient.checked_add(value)?;
+
+		if *coefficient == 0 {
+			self.terms.remove(&names);
+		}
+
+		Some(())
+	}
+
+	/// The sum, None when a coefficient overflows.
+	pub fn add(&self, other: &Poly) -> Option<Poly> {
+		let mut sum = self.clone();
+		for (names, value) in &other.terms {
+			sum.insert(names.clone(), *value)?;
+		}
+
+		Some(sum)
+	}
+
+	/// The product, None when a coefficient overflows.
+	pub fn mul(&self, other: &Poly) -> Option<Poly> {
+		let mut product = Poly::default();
+		for (a, x) in &self.terms {
+			for (b, y) in &other.terms {
+				let mut names: Vec<String> = a.iter().chain(b).cloned().collect();
+				names.sort();
+				product.insert(names, x.checked_mul(*y)?)?;
+			}
+		}
+
+		Some(product)
+	}
+
+	/// Replace the variable by a value, None when a coefficient overflows.
+	pub fn substitute(&self, name: &str, value: i64) -> Option<Poly> {
+		let mut result = Poly::default();
+		for (names, coefficient) in &self.terms {
+			let rest: Vec<String> = names.iter().filter(|n| *n != name).cloned().collect();
+			let power = (names.len() - rest.len()) as u32;
+			result.insert(rest, coefficient.checked_mul(value.checked_pow(power)?)?)?;
+		}
+
+		Some(result)
+	}
+
+	/// The coefficient of the term with exactly these variables.
+	fn coefficient(&self, names: &[&str]) -> i64 {
+		let names: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+		*self.terms.get(&names).unwrap_or(&0)
+	}
+}
+
+impl fmt::Display for Poly {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		if self.terms.is_empty() {
+			return write!(f, "0");
+		}
+
+		/* the highest degree first, the constant last */
+		let mut terms: Vec<_> = self.terms.iter().collect();
+		terms.sort_by_key(|(names, _)| Reverse(names.len()));
+
+		for (i, (names, value)) in terms.into_iter().enumerate() {
+			let sign = match (i, *value < 0) {
+				(0, true) => "-",
+				(0, false) => "",
+				(_, true) => " - ",
+				(_, false) => " + ",
+			};
+
+			let term = match (value.abs(), names.join("*")) {
+				(value, names) if names.is_empty() => value.to_string(),
+				(1, names) => names,
+				(value, names) => format!("{}*{}", value, names),
+			};
+
+			write!(f, "{}{}", sign, term)?;
+		}
+
+		Ok(())
+	}
+}
+
+/**
+ * The symbolic execution could not continue, at the instruction at ip.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Error {
+	pub ip: usize,
+	pub message: String,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "ip {}: {}", self.ip, self.message)
+	}
+}
+
+impl std::error::Error for Error {}
+
+/**
+ * Memory of the symbolic machine, a cell is None when its value is not
+ * known, which happens when it is read through an address that depends
+ * on a variable. That is fine as long as the value is never used.
+ */
+struct State {
+	memory: Vec<Option<Poly>>,
+	ip: usize,
+	base: i64,
+}
+
+impl State {
+	fn error(&self, message: String) -> Error {
+		Error {
+			ip: self.ip,
+			message,
+		}
+	}
+
+	fn overflow<T>(&self, value: Option<T>) -> Result<T, Error> {
+		value.ok_or_else(|| self.error("arithmetic overflow".to_string()))
+	}
+
+	fn cell(&self, address: usize) -> Option<Poly> {
+		match self.memory.get(address) {
+			Some(cell) => cell.clone(),
+			None => Some(Poly::default()),
+		}
+	}
+
+	fn constant(&self, value: Option<Poly>, what: &str) -> Result<i64, Error> {
+		match value.as_ref().and_then(Poly::as_constant) {
+			Some(value) => Ok(value),
+			None => Err(self.error(format!("{} depends on a variable", what))),
+		}
+	}
+
+	fn address(&self, mode: OpModes, position: usize) -> Result<Option<usize>, Error> {
+		let param = self.cell(position);
+		let address = match (mode, param) {
+			(OpModes::Immediate, _) => return Ok(None),
+			(OpModes::Parametric, param) => param,
+			(OpModes::Relative, Some(param)) => {
+				Some(self.overflow(param.add(&Poly::constant(self.base)))?)
+			}
+			(OpModes::Relative, None) => None,
+		};
+
+		/* an address that depends on a variable could point anywhere */
+		match address.as_ref().and_then(Poly::as_constant) {
+			Some(address) if address < 0 => {
+				Err(self.error(format!("negative address {}", address)))
+			}
+			Some(address) => Ok(Some(address as usize)),
+			None => Err(self.error("address depends on a variable".to_string())),
+		}
+	}
+
+	fn read(&self, mode: OpModes, position: usize) -> Option<Poly> {
+		match self.address(mode, position) {
+			Ok(Some(address)) => self.cell(address),
+			Ok(None) => self.cell(position),
+			Err(_) => None,
+		}
+	}
+
+	fn write(&mut self, mode: OpModes, position: usize, value: Option<Poly>) -> Result<(), Error> {
+		let address = match self.address(mode, position)? {
+			Some(address) => address,
+			None => return Err(self.error("write parameter in immediate mode".to_string())),
+		};
+
+		if address >= self.memory.len() {
+			self.memory.resize(address + 1, Some(Poly::default()));
+		}
+
+		self.memory[address] = value;
+		Ok(())
+	}
+
+	fn step(&mut self) -> Result<bool, Error> {
+		let instruction = self.constant(self.cell(self.ip), "the instruction")?;
+		let (code, modes) = decode(instruction).map_err(|kind| self.error(kind.to_string()))?;
+		let ip = self.ip;
+
+		match code {
+			OpCodes::Add | OpCodes::Mul | OpCodes::LT | OpCodes::EQ => {
+				let a = self.read(modes[0], ip + 1);
+				let b = self.read(modes[1], ip + 2);
+				let value = match (code, a, b) {
+					(OpCodes::Add, Some(a), Some(b)) => Some(self.overflow(a.add(&b))?),
+					(OpCodes::Mul, Some(a), Some(b)) => Some(self.overflow(a.mul(&b))?),
+					(OpCodes::Add | OpCodes::Mul, _, _) => None,
+					(_, a, b) => {
+						let a = self.constant(a, "a compared value")?;
+						let b = self.constant(b, "a compared value")?;
+						let result = if code == OpCodes::LT { a < b } else { a == b };
+						Some(Poly::constant(result as i64))
+					}
+				};
+
+				self.write(modes[2], ip + 3, value)?;
+			}
+			OpCodes::JIT | OpCodes::JIF => {
+				let condition = self.constant(self.read(modes[0], ip + 1), "a jump condition")?;
+				let target = self.constant(self.read(modes[1], ip + 2), "a jump target")?;
+
+				if (condition != 0) == (code == OpCodes::JIT) {
+					if target < 0 {
+						return Err(self.error(format!("negative jump target {}", target)));
+					}
+
+					self.ip = target as usize;
+					return Ok(true);
+				}
+			}
+			OpCodes::RA => {
+				let offset = self.constant(self.read(modes[0], ip + 1), "the base offset")?;
+				self.base = self.overflow(self.base.checked_add(offset))?;
+			}
+			OpCodes::Input | OpCodes::Output => {
+				return Err(self.error(format!("{} is not supported", code.mnemonic())));
+			}
+			OpCodes::Halt => return Ok(false),
+		}
+
+		self.ip += code.arity() + 1;
+		Ok(true)
+	}
+}
+
+/**
+ * Run the program with the cells at the given addresses replaced by
+ * variables, and return memory once it halts. A cell is None when its
+ * value could not be known, because it was read through an address
+ * that depends on a variable. Jumps, comparisons and addresses have
+ * to be constant, input and output are not supported.
+ */
+pub fn execute(program: &[i64], variables: &[(usize, &str)]) -> Result<Vec<Option<Poly>>, Error> {
+	let mut state = State {
+		memory: program
+			.iter()
+			.map(|value| Some(Poly::constant(*value)))
+			.collect(),
+		ip: 0,
+		base: 0,
+	};
+
+	for (address, name) in variables {
+		if *address >= state.memory.len() {
+			state.memory.resize(address + 1, Some(Poly::default()));
+		}
+
+		state.memory[*address] = Some(Poly::var(name));
+	}
+
+	for _ in 0..MAX_STEPS {
+		if !state.step()? {
+			return Ok(state.memory);
+		}
+	}
+
+	Err(state.error(format!("no halt within {} steps", MAX_STEPS)))
+}
+
+/**
+ * All values of x and y in the range for which the polynomial in
+ * x and y equals the target. Linear ones are solved for y directly,
+ * others are evaluated for every y. Values for which the polynomial
+ * overflows are left out.
+ */
+pub fn solve(
+	poly: &Poly,
+	target: i64,
+	x: &str,
+	y: &str,
+	range: RangeInclusive<i64>,
+) -> Vec<(i64, i64)> {
+	let mut solutions = Vec::new();
+
+	for a in range.clone() {
+		let rest = match poly.substitute(x, a) {
+			Some(rest) => rest,
+			None => continue,
+		};
+
+		if rest.degree() <= 1
+			&& rest
+				.terms
+				.keys()
+				.all(|names| names.iter().all(|name| name == y))
+		{
+			let slope = rest.coefficient(&[y]);
+			let remainder = match target.checked_sub(rest.coefficient(&[])) {
+				Some(remainder) => remainder,
+				None => continue,
+			};
+
+			if slope == 0 {
+				if remainder == 0 {
+					solutions.extend(range.clone().map(|b| (a, b)));
+				}
+			} else if remainder.checked_rem(slope) == Some(0) {
+				let b = remainder / slope;
+				if range.contains(&b) {
+					solutions.push((a, b));
+				}
+			}
+
+			continue;
+		}
+
+		for b in range.clone() {
+			if rest.substitute(y, b).and_then(|poly| poly.as_constant()) == Some(target) {
+				solutions.push((a, b));
+			}
+		}
+	}
+
+	solutions
+}
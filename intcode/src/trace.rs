@@ -0,0 +1,193 @@
+use crate::disasm::{Instruction, Operand};
+use crate::{decode, OpCodes, OpMachine, OpModes};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+/**
+ * An executed instruction, with the values it read and
+ * the address it wrote to.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Event {
+	pub ip: usize,
+	pub instruction: Instruction,
+	pub values: Vec<i64>,
+	pub target: Option<usize>,
+}
+
+impl fmt::Display for Event {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let values: Vec<String> = self.values.iter().map(|value| value.to_string()).collect();
+		let code = format!("{}", self.instruction);
+		write!(f, "{:>6}: {:<28} ; {}", self.ip, code, values.join(", "))?;
+
+		if let Some(target) = self.target {
+			write!(f, " -> [{}]", target)?;
+		}
+
+		Ok(())
+	}
+}
+
+/**
+ * A writer the trace goes to, clones of a machine share it.
+ */
+#[derive(Clone)]
+pub struct Tracer(Arc<Mutex<dyn Write + Send>>);
+
+impl Tracer {
+	pub fn new<W: Write + Send + 'static>(writer: W) -> Tracer {
+		Tracer(Arc::new(Mutex::new(writer)))
+	}
+
+	fn write(&self, event: &Event) {
+		let mut writer = self.0.lock().unwrap();
+		/* a failing trace should not stop the machine */
+		let _ = writeln!(writer, "{}", event);
+	}
+}
+
+/**
+ * Counts of what a machine executed.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Profile {
+	pub steps: u64,
+	pub counts: HashMap<OpCodes, u64>,
+	pub addresses: HashMap<usize, u64>,
+	/* one past the highest address the program occupies or wrote to */
+	pub memory: usize,
+}
+
+impl Profile {
+	pub fn record(&mut self, event: &Event) {
+		self.steps += 1;
+		*self.counts.entry(event.instruction.code).or_insert(0) += 1;
+		*self.addresses.entry(event.ip).or_insert(0) += 1;
+
+		if let Some(target) = event.target {
+			self.memory = self.memory.max(target + 1);
+		}
+	}
+
+	/// Add the counts of another profile, e.g. of a cloned machine.
+	pub fn merge(&mut self, other: &Profile) {
+		self.steps += other.steps;
+		self.memory = self.memory.max(other.memory);
+
+		for (code, count) in &other.counts {
+			*self.counts.entry(*code).or_insert(0) += count;
+		}
+
+		for (address, count) in &other.addresses {
+			*self.addresses.entry(*address).or_insert(0) += count;
+		}
+	}
+
+	/// The n most executed addresses, most executed first.
+	pub fn hottest(&self, n: usize) -> Vec<(usize, u64)> {
+		let mut addresses: Vec<(usize, u64)> =
+			self.addresses.iter().map(|(a, c)| (*a, *c)).collect();
+		addresses.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+		addresses.truncate(n);
+		addresses
+	}
+}
+
+impl fmt::Display for Profile {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		writeln!(f, "steps: {}", self.steps)?;
+		writeln!(f, "memory: {} cells", self.memory)?;
+
+		let mut counts: Vec<(&OpCodes, &u64)> = self.counts.iter().collect();
+		counts.sort_by(|a, b| b.1.cmp(a.1).then((*a.0 as i64).cmp(&(*b.0 as i64))));
+
+		writeln!(f, "opcodes:")?;
+		for (code, count) in counts {
+			let share = *count as f64 * 100.0 / self.steps.max(1) as f64;
+			writeln!(f, "  {:<4} {:>12} {:>6.2}%", code.mnemonic(), count, share)?;
+		}
+
+		writeln!(f, "hottest addresses:")?;
+		for (address, count) in self.hottest(10) {
+			writeln!(f, "  {:>6} {:>12}", address, count)?;
+		}
+
+		Ok(())
+	}
+}
+
+/// Write parameters are the last of ADD, MUL, LT, EQ and the one of IN.
+fn writes(code: OpCodes) -> bool {
+	matches!(
+		code,
+		OpCodes::Add | OpCodes::Mul | OpCodes::LT | OpCodes::EQ | OpCodes::Input
+	)
+}
+
+impl OpMachine {
+	/// Describe the instruction at the instruction pointer before it
+	/// executes, None when it would fault or wait for input.
+	pub(crate) fn event(&self) -> Option<Event> {
+		let (code, modes) = decode(self.program.get(self.ip)).ok()?;
+		let mut operands = Vec::new();
+		let mut values = Vec::new();
+		let mut target = None;
+
+		for (i, mode) in modes.iter().take(code.arity()).enumerate() {
+			let position = self.ip + i + 1;
+			operands.push(Operand::from(*mode, self.program.get(position)));
+
+			if writes(code) && i + 1 == code.arity() {
+				let param = self.program.get(position);
+				let address = match mode {
+					OpModes::Relative => self.base.checked_add(param)?,
+					_ => param,
+				};
+				target = Some(address.max(0) as usize);
+			} else {
+				values.push(self.get_value(mode, position).ok()?);
+			}
+		}
+
+		if code == OpCodes::Input {
+			values.push(*self.inputs.front()?);
+		}
+
+		Some(Event {
+			ip: self.ip,
+			instruction: Instruction { code, operands },
+			values,
+			target,
+		})
+	}
+
+	pub(crate) fn record(&mut self, event: &Event) {
+		if let Some(tracer) = &self.tracer {
+			tracer.write(event);
+		}
+
+		if let Some(profile) = &mut self.profile {
+			profile.record(event);
+		}
+	}
+
+	/// Write every executed instruction to the writer.
+	pub fn trace_to<W: Write + Send + 'static>(&mut self, writer: W) {
+		self.tracer = Some(Tracer::new(writer));
+	}
+
+	pub fn set_tracer(&mut self, tracer: Option<Tracer>) {
+		self.tracer = tracer;
+	}
+
+	/// Start counting what the machine executes in machine.profile.
+	pub fn enable_profile(&mut self) {
+		self.profile = Some(Profile {
+			memory: self.program.len(),
+			..Profile::default()
+		});
+	}
+}
@@ -0,0 +1,702 @@
+use intcode::debugger::{Debugger, Stop};
+use intcode::guard::{CodeWrite, Guard, Policy};
+use intcode::io::IterIo;
+use intcode::{disasm, symbolic, ErrorKind, Limits, OpMachine, OpModes, RunState, Snapshot};
+use std::io;
+use std::time::Duration;
+
+/*
+ * The conformance suite of the intcode machine: the examples of
+ * day02, day05 and day09, every opcode in every mode, relative
+ * writes, the edge cases of halting and waiting for input, and the
+ * limits.
+ * Every way of running a machine has a test below that runs the
+ * whole suite through it.
+ */
+
+/* the relative base set by the opcode cases, and where their data lives */
+const BASE: i64 = 25;
+const X: usize = 30;
+const Y: usize = 31;
+const RESULT: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Expect {
+	Halted,
+	NeedsInput,
+	Fault(ErrorKind),
+}
+
+struct Case {
+	name: String,
+	program: Vec<i64>,
+	inputs: Vec<i64>,
+	outputs: Vec<i64>,
+	memory: Vec<(usize, i64)>,
+	ip: Option<usize>,
+	limits: Limits,
+	expect: Expect,
+}
+
+impl Case {
+	fn new(name: &str, program: &[i64]) -> Case {
+		Case {
+			name: name.to_string(),
+			program: program.to_vec(),
+			inputs: Vec::new(),
+			outputs: Vec::new(),
+			memory: Vec::new(),
+			ip: None,
+			limits: Limits::default(),
+			expect: Expect::Halted,
+		}
+	}
+
+	fn inputs(mut self, inputs: &[i64]) -> Case {
+		self.inputs = inputs.to_vec();
+		self
+	}
+
+	fn outputs(mut self, outputs: &[i64]) -> Case {
+		self.outputs = outputs.to_vec();
+		self
+	}
+
+	fn memory(mut self, memory: &[(usize, i64)]) -> Case {
+		self.memory = memory.to_vec();
+		self
+	}
+
+	fn ip(mut self, ip: usize) -> Case {
+		self.ip = Some(ip);
+		self
+	}
+
+	fn limits(mut self, limits: Limits) -> Case {
+		self.limits = limits;
+		self
+	}
+
+	fn expect(mut self, expect: Expect) -> Case {
+		self.expect = expect;
+		self
+	}
+}
+
+/**
+ * A way of running a machine until it halts, faults or needs input,
+ * it returns the outputs and the state it stopped in.
+ */
+type Variant = fn(&mut OpMachine) -> (Vec<i64>, RunState);
+
+fn stepped(machine: &mut OpMachine) -> (Vec<i64>, RunState) {
+	loop {
+		let state = match machine.step() {
+			Ok(None) => continue,
+			Ok(Some(state)) => state,
+			Err(error) => RunState::Fault(error),
+		};
+
+		return (machine.outputs.drain(..).collect(), state);
+	}
+}
+
+fn until_output(machine: &mut OpMachine) -> (Vec<i64>, RunState) {
+	let mut outputs = Vec::new();
+	loop {
+		match machine.run_until_output() {
+			RunState::Output(output) => outputs.push(output),
+			state => return (outputs, state),
+		}
+	}
+}
+
+fn run_for(machine: &mut OpMachine) -> (Vec<i64>, RunState) {
+	loop {
+		if let Some(state) = machine.run_for(3) {
+			return (machine.outputs.drain(..).collect(), state);
+		}
+	}
+}
+
+fn run_with(machine: &mut OpMachine) -> (Vec<i64>, RunState) {
+	let mut io = IterIo::new(Vec::new());
+	let state = machine.run_with(&mut io);
+	(io.outputs, state)
+}
+
+fn instrumented(machine: &mut OpMachine) -> (Vec<i64>, RunState) {
+	machine.enable_profile();
+	machine.trace_to(io::sink());
+	machine.guard = Some(Guard::new(Policy::Allow));
+	until_output(machine)
+}
+
+fn forked(machine: &mut OpMachine) -> (Vec<i64>, RunState) {
+	loop {
+		let mut fork = machine.fork();
+		let state = fork.step();
+		*machine = fork;
+
+		let state = match state {
+			Ok(None) => continue,
+			Ok(Some(state)) => state,
+			Err(error) => RunState::Fault(error),
+		};
+
+		return (machine.outputs.drain(..).collect(), state);
+	}
+}
+
+fn snapshots(machine: &mut OpMachine) -> (Vec<i64>, RunState) {
+	loop {
+		let text = machine.snapshot().to_string();
+		let snapshot: Snapshot = text.parse().expect("the snapshot should parse");
+		/* a snapshot holds the state, not the limits or the steps taken */
+		let (limits, steps) = (machine.limits, machine.steps);
+		*machine = OpMachine::from(&snapshot);
+		machine.limits = limits;
+		machine.steps = steps;
+
+		let state = match machine.step() {
+			Ok(None) => continue,
+			Ok(Some(state)) => state,
+			Err(error) => RunState::Fault(error),
+		};
+
+		return (machine.outputs.drain(..).collect(), state);
+	}
+}
+
+fn debugger(machine: &mut OpMachine) -> (Vec<i64>, RunState) {
+	let mut debugger = Debugger::new(machine.clone());
+	debugger.opcodes.push(intcode::OpCodes::Output);
+
+	let state = loop {
+		if let Stop::State(state) = debugger.cont() {
+			break state;
+		}
+	};
+
+	*machine = debugger.machine;
+	(machine.outputs.drain(..).collect(), state)
+}
+
+/// The parameter that refers to the value at address in the mode.
+fn param(mode: OpModes, address: usize, value: i64) -> i64 {
+	match mode {
+		OpModes::Parametric => address as i64,
+		OpModes::Immediate => value,
+		OpModes::Relative => address as i64 - BASE,
+	}
+}
+
+/// The instruction word of the opcode with the modes of its parameters.
+fn word(code: i64, modes: &[OpModes]) -> i64 {
+	modes
+		.iter()
+		.rev()
+		.fold(0, |word, mode| word * 10 + *mode as i64)
+		* 100 + code
+}
+
+/// A program that sets the relative base, runs the instruction
+/// and halts, with room for the data at X, Y and RESULT.
+fn program(instruction: &[i64], x: i64, y: i64) -> Vec<i64> {
+	let mut program = vec![109, BASE];
+	program.extend(instruction);
+	program.push(99);
+	program.resize(RESULT + 1, 0);
+	program[X] = x;
+	program[Y] = y;
+	program
+}
+
+type Operation = fn(i64, i64) -> i64;
+
+const MODES: [OpModes; 3] = [OpModes::Parametric, OpModes::Immediate, OpModes::Relative];
+
+fn examples() -> Vec<Case> {
+	let compare = [
+		3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36, 98, 0, 0,
+		1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000, 1, 20, 4, 20,
+		1105, 1, 46, 98, 99,
+	];
+	let quine = [
+		109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+	];
+
+	let mut cases = vec![
+		Case::new("day02 add", &[1, 0, 0, 0, 99]).memory(&[(0, 2)]),
+		Case::new("day02 mul", &[2, 3, 0, 3, 99]).memory(&[(3, 6)]),
+		Case::new("day02 mul past the halt", &[2, 4, 4, 5, 99, 0]).memory(&[(5, 9801)]),
+		Case::new("day02 overwrite halt", &[1, 1, 1, 4, 99, 5, 6, 0, 99])
+			.memory(&[(0, 30), (4, 2)]),
+		Case::new("day02 program", &[1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50])
+			.memory(&[(0, 3500), (3, 70)]),
+		Case::new("day05 echo", &[3, 0, 4, 0, 99])
+			.inputs(&[-17])
+			.outputs(&[-17]),
+		Case::new("day05 mul modes", &[1002, 4, 3, 4, 33]).memory(&[(4, 99)]),
+		Case::new("day05 negative", &[1101, 100, -1, 4, 0]).memory(&[(4, 99)]),
+		Case::new("day09 quine", &quine).outputs(&quine),
+		Case::new(
+			"day09 16 digits",
+			&[1102, 34915192, 34915192, 7, 4, 7, 99, 0],
+		)
+		.outputs(&[1219070632396864]),
+		Case::new("day09 large", &[104, 1125899906842624, 99]).outputs(&[1125899906842624]),
+	];
+
+	for input in [7, 8, 9] {
+		let equal = (input == 8) as i64;
+		let less = (input < 8) as i64;
+		let tests = [
+			(
+				"equal to 8, position",
+				vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8],
+				equal,
+			),
+			(
+				"less than 8, position",
+				vec![3, 9, 7, 9, 10, 9, 4, 9, 99, -1, 8],
+				less,
+			),
+			(
+				"equal to 8, immediate",
+				vec![3, 3, 1108, -1, 8, 3, 4, 3, 99],
+				equal,
+			),
+			(
+				"less than 8, immediate",
+				vec![3, 3, 1107, -1, 8, 3, 4, 3, 99],
+				less,
+			),
+			(
+				"compare to 8",
+				compare.to_vec(),
+				999 + (input > 7) as i64 + (input > 8) as i64,
+			),
+		];
+
+		for (name, program, output) in tests {
+			let name = format!("day05 {} with {}", name, input);
+			cases.push(
+				Case::new(&name, &program)
+					.inputs(&[input])
+					.outputs(&[output]),
+			);
+		}
+	}
+
+	for input in [0, 3] {
+		let output = (input != 0) as i64;
+		let position = [3, 12, 6, 12, 15, 1, 13, 14, 13, 4, 13, 99, -1, 0, 1, 9];
+		let immediate = [3, 3, 1105, -1, 9, 1101, 0, 0, 12, 4, 12, 99, 1];
+
+		let name = format!("day05 jump, position with {}", input);
+		cases.push(
+			Case::new(&name, &position)
+				.inputs(&[input])
+				.outputs(&[output]),
+		);
+		let name = format!("day05 jump, immediate with {}", input);
+		cases.push(
+			Case::new(&name, &immediate)
+				.inputs(&[input])
+				.outputs(&[output]),
+		);
+	}
+
+	cases
+}
+
+fn opcodes() -> Vec<Case> {
+	let mut cases = Vec::new();
+	let operations: [(i64, Operation); 4] = [
+		(1, |x, y| x + y),
+		(2, |x, y| x * y),
+		(7, |x, y| (x < y) as i64),
+		(8, |x, y| (x == y) as i64),
+	];
+
+	for (code, operation) in operations {
+		for (x, y) in [(6, 7), (7, 7), (-3, 2)] {
+			for a in MODES {
+				for b in MODES {
+					for c in MODES {
+						let name = format!(
+							"opcode {} modes {:?} {:?} {:?} on {} {}",
+							code, a, b, c, x, y
+						);
+						let instruction = [
+							word(code, &[a, b, c]),
+							param(a, X, x),
+							param(b, Y, y),
+							param(c, RESULT, 0),
+						];
+						let case = Case::new(&name, &program(&instruction, x, y));
+
+						cases.push(match c {
+							OpModes::Immediate => {
+								case.expect(Expect::Fault(ErrorKind::ImmediateWrite)).ip(2)
+							}
+							_ => case.memory(&[(RESULT, operation(x, y))]),
+						});
+					}
+				}
+			}
+		}
+	}
+
+	/* the jumps land on an output of 1, when they do not jump they output 0 */
+	for (code, jumps) in [(5, true), (6, false)] {
+		for condition in [0, 5] {
+			for a in MODES {
+				for b in MODES {
+					let name = format!("opcode {} modes {:?} {:?} on {}", code, a, b, condition);
+					let instruction = [
+						word(code, &[a, b]),
+						param(a, X, condition),
+						param(b, Y, 10),
+						104,
+						0,
+						99,
+						0,
+						0,
+						104,
+						1,
+					];
+					let output = ((condition != 0) == jumps) as i64;
+					cases.push(
+						Case::new(&name, &program(&instruction, condition, 10)).outputs(&[output]),
+					);
+				}
+			}
+		}
+	}
+
+	for mode in MODES {
+		let name = format!("opcode 3 mode {:?}", mode);
+		let case = Case::new(
+			&name,
+			&program(&[word(3, &[mode]), param(mode, RESULT, 0)], 0, 0),
+		)
+		.inputs(&[42]);
+		cases.push(match mode {
+			OpModes::Immediate => case.expect(Expect::Fault(ErrorKind::ImmediateWrite)).ip(2),
+			_ => case.memory(&[(RESULT, 42)]),
+		});
+
+		let name = format!("opcode 4 mode {:?}", mode);
+		cases.push(
+			Case::new(
+				&name,
+				&program(&[word(4, &[mode]), param(mode, X, -8)], -8, 0),
+			)
+			.outputs(&[-8]),
+		);
+
+		/* the base goes from 25 to 32, where 77 is */
+		let name = format!("opcode 9 mode {:?}", mode);
+		let mut program = program(&[word(9, &[mode]), param(mode, X, 7), 204, 0], 7, 0);
+		program[RESULT] = 77;
+		cases.push(Case::new(&name, &program).outputs(&[77]));
+	}
+
+	cases
+}
+
+fn relative() -> Vec<Case> {
+	vec![
+		Case::new(
+			"relative write past the program",
+			&[109, 10, 21101, 3, 4, -3, 99],
+		)
+		.memory(&[(7, 7)]),
+		Case::new(
+			"relative write far away",
+			&[109, 1000, 21101, 3, 4, 5, 204, 5, 99],
+		)
+		.outputs(&[7])
+		.memory(&[(1005, 7)]),
+		Case::new("relative input", &[109, 7, 203, 1, 204, 1, 99])
+			.inputs(&[5])
+			.outputs(&[5])
+			.memory(&[(8, 5)]),
+		Case::new("relative base goes down", &[109, 20, 109, -15, 204, -1, 99]).outputs(&[204]),
+		Case::new("relative write below zero", &[109, -5, 21101, 1, 1, 4, 99])
+			.expect(Expect::Fault(ErrorKind::NegativeAddress(-1)))
+			.ip(2),
+		Case::new("relative read below zero", &[109, -5, 204, 1, 99])
+			.expect(Expect::Fault(ErrorKind::NegativeAddress(-4)))
+			.ip(2),
+		Case::new("memory past the program is zero", &[4, 100, 99]).outputs(&[0]),
+	]
+}
+
+fn edges() -> Vec<Case> {
+	vec![
+		Case::new("halt", &[99]).ip(0),
+		Case::new("halt with inputs left", &[99])
+			.inputs(&[1, 2])
+			.ip(0),
+		Case::new("halt after output", &[104, 1, 99])
+			.outputs(&[1])
+			.ip(2),
+		Case::new("needs input", &[3, 0, 99])
+			.expect(Expect::NeedsInput)
+			.ip(0),
+		Case::new("needs input after output", &[104, 1, 3, 0, 99])
+			.outputs(&[1])
+			.expect(Expect::NeedsInput)
+			.ip(2),
+		Case::new("needs a second input", &[3, 0, 3, 0, 99])
+			.inputs(&[1])
+			.expect(Expect::NeedsInput)
+			.memory(&[(0, 1)])
+			.ip(2),
+		Case::new("invalid opcode", &[1, 0, 0, 0, 42])
+			.expect(Expect::Fault(ErrorKind::InvalidOpcode(42)))
+			.ip(4),
+		Case::new("invalid mode", &[304, 0, 99])
+			.expect(Expect::Fault(ErrorKind::InvalidMode(3)))
+			.ip(0),
+		Case::new("jump below zero", &[1105, 1, -1])
+			.expect(Expect::Fault(ErrorKind::NegativeAddress(-1)))
+			.ip(0),
+		Case::new("jump to the halt", &[1106, 0, 4, 0, 99]).ip(4),
+		Case::new("add overflows", &[1101, i64::MAX, 1, 0, 99])
+			.expect(Expect::Fault(ErrorKind::Overflow))
+			.memory(&[(0, 1101)])
+			.ip(0),
+		Case::new("multiply overflows", &[1102, i64::MAX, 2, 0, 99])
+			.expect(Expect::Fault(ErrorKind::Overflow))
+			.ip(0),
+		Case::new("relative base overflows", &[109, i64::MAX, 109, 1, 99])
+			.expect(Expect::Fault(ErrorKind::Overflow))
+			.ip(2),
+		Case::new("relative address overflows", &[109, i64::MAX, 204, 1, 99])
+			.expect(Expect::Fault(ErrorKind::Overflow))
+			.ip(2),
+	]
+}
+
+fn limits() -> Vec<Case> {
+	vec![
+		Case::new("step limit", &[1105, 1, 0])
+			.limits(Limits::default().with_steps(10))
+			.expect(Expect::Fault(ErrorKind::StepLimit(10)))
+			.ip(0),
+		Case::new("step limit not reached", &[1101, 1, 1, 0, 99])
+			.limits(Limits::default().with_steps(2))
+			.memory(&[(0, 2)]),
+		Case::new("memory limit on a read", &[4, 100, 99])
+			.limits(Limits::default().with_memory(50))
+			.expect(Expect::Fault(ErrorKind::MemoryLimit(100)))
+			.ip(0),
+		Case::new("memory limit on a write", &[1101, 1, 1, 60, 99])
+			.limits(Limits::default().with_memory(50))
+			.expect(Expect::Fault(ErrorKind::MemoryLimit(60)))
+			.ip(0),
+		Case::new("deadline", &[1105, 1, 0])
+			.limits(Limits::default().with_timeout(Duration::from_secs(0)))
+			.expect(Expect::Fault(ErrorKind::Deadline))
+			.ip(0),
+	]
+}
+
+fn suite() -> Vec<Case> {
+	let mut cases = examples();
+	cases.extend(opcodes());
+	cases.extend(relative());
+	cases.extend(edges());
+	cases.extend(limits());
+	cases
+}
+
+fn conform(variant: Variant) {
+	for case in suite() {
+		let mut machine = OpMachine::new(case.program.clone());
+		machine.inputs.extend(&case.inputs);
+		machine.limits = case.limits;
+
+		let (outputs, state) = variant(&mut machine);
+		assert_eq!(outputs, case.outputs, "outputs of {}", case.name);
+
+		let stopped = match state {
+			RunState::Halted => Expect::Halted,
+			RunState::NeedsInput => Expect::NeedsInput,
+			RunState::Fault(error) => Expect::Fault(error.kind),
+			RunState::Output(_) => panic!("{} stopped on an output", case.name),
+		};
+		assert_eq!(stopped, case.expect, "state of {}", case.name);
+
+		if let Some(ip) = case.ip {
+			assert_eq!(machine.ip, ip, "ip of {}", case.name);
+		}
+
+		for (address, value) in &case.memory {
+			assert_eq!(
+				machine.program.get(*address),
+				*value,
+				"address {} of {}",
+				address,
+				case.name
+			);
+		}
+	}
+
+	/* a machine that waits for input continues once it has some */
+	let mut machine = OpMachine::new(vec![3, 9, 4, 9, 3, 9, 4, 9, 99, 0]);
+	assert_eq!(variant(&mut machine), (vec![], RunState::NeedsInput));
+	machine.register_input(5);
+	assert_eq!(variant(&mut machine), (vec![5], RunState::NeedsInput));
+	machine.register_input(6);
+	assert_eq!(variant(&mut machine), (vec![6], RunState::Halted));
+	assert_eq!(variant(&mut machine), (vec![], RunState::Halted));
+}
+
+#[test]
+fn step() {
+	conform(stepped);
+}
+
+#[test]
+fn run_until_output() {
+	conform(until_output);
+}
+
+#[test]
+fn run_for_chunks() {
+	conform(run_for);
+}
+
+#[test]
+fn run_with_io() {
+	conform(run_with);
+}
+
+#[test]
+fn traced_profiled_and_guarded() {
+	conform(instrumented);
+}
+
+#[test]
+fn fork_every_step() {
+	conform(forked);
+}
+
+#[test]
+fn snapshot_every_step() {
+	conform(snapshots);
+}
+
+#[test]
+fn debugger_with_breakpoints() {
+	conform(debugger);
+}
+
+/* the second add writes over the first, which was executed by then */
+const PATCH: [i64; 9] = [1101, 5, 6, 0, 1101, 7, 8, 0, 99];
+
+fn guarded(guard: Guard) -> (OpMachine, RunState) {
+	let mut machine = OpMachine::new(PATCH.to_vec());
+	machine.guard = Some(guard);
+	let state = until_output(&mut machine).1;
+	(machine, state)
+}
+
+#[test]
+fn guard_faults_code_writes() {
+	let (machine, state) = guarded(Guard::new(Policy::Fault));
+	match state {
+		RunState::Fault(error) => assert_eq!((error.kind, error.ip), (ErrorKind::CodeWrite(0), 4)),
+		state => panic!("the code write was not caught: {:?}", state),
+	}
+	assert_eq!(machine.program.get(0), 11);
+}
+
+#[test]
+fn guard_warns_on_code_writes() {
+	let (mut machine, state) = guarded(Guard::new(Policy::Warn));
+	assert_eq!(state, RunState::Halted);
+	assert_eq!(machine.program.get(0), 15);
+
+	/* a patch from outside is not attributed to an instruction */
+	machine.write(4, 2).unwrap();
+	machine.write(20, 1).unwrap();
+	assert_eq!(
+		machine.guard.unwrap().warnings,
+		[
+			CodeWrite {
+				ip: Some(4),
+				address: 0,
+				old: 11,
+				new: 15,
+			},
+			CodeWrite {
+				ip: None,
+				address: 4,
+				old: 1101,
+				new: 2,
+			},
+		]
+	);
+}
+
+#[test]
+fn guard_faults_read_only_writes() {
+	let (machine, state) = guarded(Guard::new(Policy::Allow).with_read_only(0..1));
+	match state {
+		RunState::Fault(error) => assert_eq!((error.kind, error.ip), (ErrorKind::ReadOnly(0), 0)),
+		state => panic!("the read-only write was not caught: {:?}", state),
+	}
+	assert_eq!(machine.program.get(0), 1101);
+
+	let mut machine = OpMachine::new(PATCH.to_vec());
+	machine.guard = Some(Guard::new(Policy::Fault).with_read_only(8..9));
+	assert_eq!(machine.write(8, 1), Err(ErrorKind::ReadOnly(8)));
+	assert_eq!(machine.write(7, 1), Ok(()));
+}
+
+/* the disassembler takes any image, the faulting ones included */
+#[test]
+fn disassemble() {
+	for case in suite() {
+		assert!(!disasm::listing(&case.program).is_empty(), "{}", case.name);
+	}
+
+	assert!(disasm::listing(&[21102, i64::MAX, 2, 0, 99]).contains("MUL"));
+}
+
+/* the symbolic machine has no input or output, it runs the rest
+ * and fails on the overflows */
+#[test]
+fn symbolic() {
+	for case in suite() {
+		if case.expect == Expect::Fault(ErrorKind::Overflow) {
+			assert!(
+				symbolic::execute(&case.program, &[]).is_err(),
+				"{} did not fail",
+				case.name
+			);
+			continue;
+		}
+
+		if !case.inputs.is_empty() || !case.outputs.is_empty() || case.expect != Expect::Halted {
+			continue;
+		}
+
+		let memory = symbolic::execute(&case.program, &[])
+			.unwrap_or_else(|error| panic!("{} failed: {}", case.name, error));
+		for (address, value) in &case.memory {
+			let cell = memory
+				.get(*address)
+				.cloned()
+				.flatten()
+				.and_then(|value| value.as_constant());
+			assert_eq!(cell, Some(*value), "address {} of {}", address, case.name);
+		}
+	}
+}